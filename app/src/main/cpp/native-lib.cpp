@@ -1,5 +1,6 @@
 #include <jni.h>
 #include <string>
+#include <algorithm>
 #include <android/native_window.h>
 #include <android/native_window_jni.h>
 #include <android/asset_manager_jni.h>
@@ -9,48 +10,180 @@
 
 #define LOG_TAG "NativeBridge"
 
-extern "C" void surfaceRedrawNeeded();
-extern "C" void surfaceCreated(ANativeWindow *pWindow);
-extern "C" void surfaceChanged(uint32_t width, uint32_t height);
-extern "C" void surfaceDestroyed();
+typedef void (*ErrorCallback)(int32_t code, const char *message);
+typedef void (*ImeCallback)(bool enabled, float x, float y);
+typedef void (*SurfaceFormatCallback)(int32_t format);
+typedef void (*SurfaceReleasedCallback)(int64_t releaseId);
+
+extern "C" int32_t surfaceRedrawNeeded();
+extern "C" int32_t surfaceCreated(ANativeWindow *pWindow);
+extern "C" int32_t surfaceChanged(uint32_t width, uint32_t height, int32_t format, float density);
+extern "C" void setSurfaceFormatCallback(SurfaceFormatCallback callback);
+extern "C" int32_t surfaceDestroyed();
+extern "C" int32_t surfaceTextureDestroyed(int64_t releaseId);
+extern "C" void setSurfaceReleasedCallback(SurfaceReleasedCallback callback);
 extern "C" void runGameLoop();
-extern "C" void stopGame();
-extern "C" void touchEvent(uint32_t pointerId, uint32_t action, float x, float y);
-extern "C" void onResume();
-extern "C" void onPause();
-extern "C" void initialize(AAssetManager *pAssetManager);
+extern "C" int32_t stopGame();
+extern "C" bool hitTest(float x, float y);
+extern "C" int32_t touchEvents(int32_t action, int32_t actionIndex, int32_t pointerCount, int32_t historySize,
+                               const float *data, size_t len, const int64_t *eventTimes, size_t timesLen);
+extern "C" int32_t mouseEvent(int64_t eventTime, int32_t action, int32_t actionButton, float x, float y, float dx, float dy,
+                              float scrollX, float scrollY);
+extern "C" int32_t keyEvent(int64_t eventTime, int32_t keycode, int32_t action, int32_t metaState, int32_t unicode);
+extern "C" int32_t gamepadConnected(int64_t eventTime, int32_t deviceId, const char *name);
+extern "C" int32_t gamepadDisconnected(int64_t eventTime, int32_t deviceId);
+extern "C" bool isGamepadButton(int32_t keycode);
+extern "C" int32_t gamepadButton(int64_t eventTime, int32_t deviceId, int32_t keycode, bool pressed);
+extern "C" int32_t gamepadAxis(int64_t eventTime, int32_t deviceId, int32_t axis, float value);
+extern "C" int32_t backPressed(int64_t eventTime, bool *consumed);
+extern "C" int32_t setSafeAreaInsets(float left, float top, float right, float bottom);
+extern "C" int32_t onResume();
+extern "C" int32_t onPause();
+extern "C" int32_t initialize(AAssetManager *pAssetManager);
+extern "C" void setErrorCallback(ErrorCallback callback);
+extern "C" void setImeCallback(ImeCallback callback);
+extern "C" int32_t imePreedit(int64_t eventTime, const char *text, int32_t cursorStart, int32_t cursorEnd);
+extern "C" int32_t imeCommit(int64_t eventTime, const char *text);
+extern "C" int32_t setTargetFrameRate(float fps);
+extern "C" int32_t setIdleFrameRate(float fps, int64_t idleAfterMs);
+extern "C" int32_t setUpdateMode(bool reactive, int64_t maxWaitMs);
+extern "C" int32_t setRenderResolution(uint32_t width, uint32_t height);
+extern "C" int32_t setRenderScale(float scale);
 extern "C" void drainCommandQueue();
 extern "C" void activityCreated(JavaVM *jvm, jobject activity);
 extern "C" void activityDestroyed();
 
+static JavaVM *gJvm = nullptr;
+static jclass gNativeBridgeClass = nullptr;
+static jmethodID gOnNativeError = nullptr;
+static jmethodID gOnImeRequest = nullptr;
+static jmethodID gOnSurfaceFormatRequest = nullptr;
+static jmethodID gOnSurfaceReleased = nullptr;
+
+static JNIEnv *getEnv() {
+    JNIEnv *env = nullptr;
+    if (gJvm->GetEnv(reinterpret_cast<void **>(&env), JNI_VERSION_1_6) == JNI_EDETACHED) {
+        gJvm->AttachCurrentThread(&env, nullptr);
+    }
+    return env;
+}
+
+// errors are reported from the game thread, forward them to NativeBridge.onNativeError
+static void onNativeError(int32_t code, const char *message) {
+    ALOGE("native error %d: %s", code, message);
+    JNIEnv *env = getEnv();
+    jstring jMessage = env->NewStringUTF(message);
+    env->CallStaticVoidMethod(gNativeBridgeClass, gOnNativeError, code, jMessage);
+    env->DeleteLocalRef(jMessage);
+}
+
+// the game window wants the soft keyboard shown or hidden, forward it to NativeBridge.onImeRequest
+static void onImeRequest(bool enabled, float x, float y) {
+    JNIEnv *env = getEnv();
+    env->CallStaticVoidMethod(gNativeBridgeClass, gOnImeRequest, enabled, x, y);
+}
+
+// the game wants surface buffers in another PixelFormat, forward it to NativeBridge.onSurfaceFormatRequest
+static void onSurfaceFormatRequest(int32_t format) {
+    JNIEnv *env = getEnv();
+    env->CallStaticVoidMethod(gNativeBridgeClass, gOnSurfaceFormatRequest, format);
+}
+
+// the game let go of a destroyed SurfaceTexture, forward it to NativeBridge.onSurfaceReleased
+static void onSurfaceReleased(int64_t releaseId) {
+    JNIEnv *env = getEnv();
+    env->CallStaticVoidMethod(gNativeBridgeClass, gOnSurfaceReleased, static_cast<jlong>(releaseId));
+}
 
 extern "C"
-JNIEXPORT void JNICALL
+JNIEXPORT jint JNICALL
+JNI_OnLoad(JavaVM *vm, void *reserved) {
+    gJvm = vm;
+    JNIEnv *env = getEnv();
+    jclass bridgeClass = env->FindClass("com/rqg/bevy/surface/NativeBridge");
+    gNativeBridgeClass = reinterpret_cast<jclass>(env->NewGlobalRef(bridgeClass));
+    env->DeleteLocalRef(bridgeClass);
+    gOnNativeError = env->GetStaticMethodID(gNativeBridgeClass, "onNativeError", "(ILjava/lang/String;)V");
+    setErrorCallback(onNativeError);
+    gOnImeRequest = env->GetStaticMethodID(gNativeBridgeClass, "onImeRequest", "(ZFF)V");
+    setImeCallback(onImeRequest);
+    gOnSurfaceFormatRequest = env->GetStaticMethodID(gNativeBridgeClass, "onSurfaceFormatRequest", "(I)V");
+    setSurfaceFormatCallback(onSurfaceFormatRequest);
+    gOnSurfaceReleased = env->GetStaticMethodID(gNativeBridgeClass, "onSurfaceReleased", "(J)V");
+    setSurfaceReleasedCallback(onSurfaceReleased);
+    return JNI_VERSION_1_6;
+}
+
+
+extern "C"
+JNIEXPORT jint JNICALL
 Java_com_rqg_bevy_surface_NativeBridge_00024Companion_surfaceRedrawNeeded(JNIEnv *env, jobject thiz) {
-    surfaceRedrawNeeded();
+    return surfaceRedrawNeeded();
 }
 
 
 extern "C"
-JNIEXPORT void JNICALL
-Java_com_rqg_bevy_surface_NativeBridge_00024Companion_surfaceChanged(JNIEnv *env, jobject thiz, jint width, jint height) {
-    surfaceChanged(width, height);
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_surfaceChanged(JNIEnv *env, jobject thiz, jint width, jint height,
+                                                                     jint format, jfloat density) {
+    return surfaceChanged(width, height, format, density);
 }
 extern "C"
-JNIEXPORT void JNICALL
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_setSafeAreaInsets(JNIEnv *env, jobject thiz, jfloat left, jfloat top,
+                                                                        jfloat right, jfloat bottom) {
+    return setSafeAreaInsets(left, top, right, bottom);
+}
+extern "C"
+JNIEXPORT jint JNICALL
 Java_com_rqg_bevy_surface_NativeBridge_00024Companion_surfaceDestroyed(JNIEnv *env, jobject thiz) {
-    surfaceDestroyed();
+    return surfaceDestroyed();
 }
 
 extern "C"
-JNIEXPORT void JNICALL
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_surfaceTextureDestroyed(JNIEnv *env, jobject thiz, jlong release_id) {
+    return surfaceTextureDestroyed(release_id);
+}
+
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_imePreedit(JNIEnv *env, jobject thiz, jlong event_time, jstring text,
+                                                                 jint cursor_start, jint cursor_end) {
+    auto chars = env->GetStringUTFChars(text, nullptr);
+    auto result = imePreedit(event_time, chars, cursor_start, cursor_end);
+    env->ReleaseStringUTFChars(text, chars);
+    return result;
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_imeCommit(JNIEnv *env, jobject thiz, jlong event_time, jstring text) {
+    auto chars = env->GetStringUTFChars(text, nullptr);
+    auto result = imeCommit(event_time, chars);
+    env->ReleaseStringUTFChars(text, chars);
+    return result;
+}
+
+extern "C"
+JNIEXPORT jboolean JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_backPressed(JNIEnv *env, jobject thiz, jlong event_time) {
+    bool consumed = false;
+    auto result = backPressed(event_time, &consumed);
+    if (result != 0) {
+        ALOGE("backPressed failed: %d", result);
+    }
+    return consumed;
+}
+
+extern "C"
+JNIEXPORT jint JNICALL
 Java_com_rqg_bevy_surface_NativeBridge_00024Companion_onResume(JNIEnv *env, jobject thiz) {
-    onResume();
+    return onResume();
 }
 extern "C"
-JNIEXPORT void JNICALL
+JNIEXPORT jint JNICALL
 Java_com_rqg_bevy_surface_NativeBridge_00024Companion_onPause(JNIEnv *env, jobject thiz) {
-    onPause();
+    return onPause();
 }
 extern "C"
 JNIEXPORT void JNICALL
@@ -58,20 +191,79 @@ Java_com_rqg_bevy_surface_NativeBridge_00024Companion_runGameLoop(JNIEnv *env, j
     runGameLoop();
 }
 extern "C"
-JNIEXPORT void JNICALL
+JNIEXPORT jint JNICALL
 Java_com_rqg_bevy_surface_NativeBridge_00024Companion_stopGame(JNIEnv *env, jobject thiz) {
-    stopGame();
+    return stopGame();
 }
 extern "C"
-JNIEXPORT void JNICALL
-Java_com_rqg_bevy_surface_NativeBridge_00024Companion_touchEvent(JNIEnv *env, jobject thiz, jint pointer_id, jint acton, jfloat x, jfloat y) {
-    touchEvent(pointer_id, acton, x, y);
+JNIEXPORT jboolean JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_hitTest(JNIEnv *env, jobject thiz, jfloat x, jfloat y) {
+    return hitTest(x, y);
 }
 extern "C"
-JNIEXPORT void JNICALL
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_touchEvents(JNIEnv *env, jobject thiz, jint action, jint action_index,
+                                                                  jint pointer_count, jint history_size, jfloatArray data,
+                                                                  jlongArray event_times) {
+    auto len = env->GetArrayLength(data);
+    auto elements = env->GetFloatArrayElements(data, nullptr);
+    auto times_len = env->GetArrayLength(event_times);
+    auto times = env->GetLongArrayElements(event_times, nullptr);
+    auto result = touchEvents(action, action_index, pointer_count, history_size, elements, len, times, times_len);
+    env->ReleaseLongArrayElements(event_times, times, JNI_ABORT);
+    env->ReleaseFloatArrayElements(data, elements, JNI_ABORT);
+    return result;
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_mouseEvent(JNIEnv *env, jobject thiz, jlong event_time, jint action,
+                                                                 jint action_button, jfloat x, jfloat y, jfloat dx, jfloat dy,
+                                                                 jfloat scroll_x, jfloat scroll_y) {
+    return mouseEvent(event_time, action, action_button, x, y, dx, dy, scroll_x, scroll_y);
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_keyEvent(JNIEnv *env, jobject thiz, jlong event_time, jint keycode,
+                                                               jint action, jint meta_state, jint unicode) {
+    return keyEvent(event_time, keycode, action, meta_state, unicode);
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_gamepadConnected(JNIEnv *env, jobject thiz, jlong event_time, jint device_id,
+                                                                       jstring name) {
+    auto chars = env->GetStringUTFChars(name, nullptr);
+    auto result = gamepadConnected(event_time, device_id, chars);
+    env->ReleaseStringUTFChars(name, chars);
+    return result;
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_gamepadDisconnected(JNIEnv *env, jobject thiz, jlong event_time,
+                                                                          jint device_id) {
+    return gamepadDisconnected(event_time, device_id);
+}
+extern "C"
+JNIEXPORT jboolean JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_isGamepadButton(JNIEnv *env, jobject thiz, jint keycode) {
+    return isGamepadButton(keycode);
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_gamepadButton(JNIEnv *env, jobject thiz, jlong event_time,
+                                                                    jint device_id, jint keycode, jboolean pressed) {
+    return gamepadButton(event_time, device_id, keycode, pressed);
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_gamepadAxis(JNIEnv *env, jobject thiz, jlong event_time,
+                                                                  jint device_id, jint axis, jfloat value) {
+    return gamepadAxis(event_time, device_id, axis, value);
+}
+extern "C"
+JNIEXPORT jint JNICALL
 Java_com_rqg_bevy_surface_NativeBridge_00024Companion_surfaceCreated(JNIEnv *env, jobject thiz, jobject surface) {
     auto win = ANativeWindow_fromSurface(env, surface);
-    surfaceCreated(win);
+    return surfaceCreated(win);
 }
 extern "C"
 JNIEXPORT void JNICALL
@@ -91,7 +283,32 @@ Java_com_rqg_bevy_surface_NativeBridge_00024Companion_activityDestroyed(JNIEnv *
     activityDestroyed();
 }
 extern "C"
-JNIEXPORT void JNICALL
+JNIEXPORT jint JNICALL
 Java_com_rqg_bevy_surface_NativeBridge_00024Companion_initialize(JNIEnv *env, jobject thiz, jobject asset_manager) {
-    initialize(AAssetManager_fromJava(env, asset_manager));
-}
\ No newline at end of file
+    return initialize(AAssetManager_fromJava(env, asset_manager));
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_setTargetFrameRate(JNIEnv *env, jobject thiz, jfloat fps) {
+    return setTargetFrameRate(fps);
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_setIdleFrameRate(JNIEnv *env, jobject thiz, jfloat fps, jlong idle_after_ms) {
+    return setIdleFrameRate(fps, idle_after_ms);
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_setUpdateMode(JNIEnv *env, jobject thiz, jboolean reactive, jlong max_wait_ms) {
+    return setUpdateMode(reactive, max_wait_ms);
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_setRenderResolution(JNIEnv *env, jobject thiz, jint width, jint height) {
+    return setRenderResolution(std::max(width, 0), std::max(height, 0));
+}
+extern "C"
+JNIEXPORT jint JNICALL
+Java_com_rqg_bevy_surface_NativeBridge_00024Companion_setRenderScale(JNIEnv *env, jobject thiz, jfloat scale) {
+    return setRenderScale(scale);
+}
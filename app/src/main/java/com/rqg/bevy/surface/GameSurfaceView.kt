@@ -0,0 +1,79 @@
+package com.rqg.bevy.surface
+
+import android.content.Context
+import android.text.InputType
+import android.os.SystemClock
+import android.util.AttributeSet
+import android.view.KeyEvent
+import android.view.SurfaceView
+import android.view.inputmethod.BaseInputConnection
+import android.view.inputmethod.EditorInfo
+import android.view.inputmethod.InputConnection
+import android.view.inputmethod.InputMethodManager
+
+/**
+ * A [SurfaceView] that can receive text from the soft keyboard and forwards it to bevy as IME events.
+ */
+class GameSurfaceView @JvmOverloads constructor(
+    context: Context,
+    attrs: AttributeSet? = null,
+) : SurfaceView(context, attrs) {
+
+    init {
+        isFocusable = true
+        isFocusableInTouchMode = true
+    }
+
+    fun showIme(show: Boolean) {
+        val imm = context.getSystemService(InputMethodManager::class.java)
+        if (show) {
+            requestFocus()
+            imm.showSoftInput(this, 0)
+        } else {
+            imm.hideSoftInputFromWindow(windowToken, 0)
+        }
+    }
+
+    override fun onCheckIsTextEditor(): Boolean = true
+
+    override fun onCreateInputConnection(outAttrs: EditorInfo): InputConnection {
+        outAttrs.inputType = InputType.TYPE_CLASS_TEXT
+        outAttrs.imeOptions = EditorInfo.IME_FLAG_NO_FULLSCREEN or EditorInfo.IME_FLAG_NO_EXTRACT_UI
+        return ImeInputConnection()
+    }
+
+    private inner class ImeInputConnection : BaseInputConnection(this, false) {
+        private var composing = ""
+
+        override fun setComposingText(text: CharSequence, newCursorPosition: Int): Boolean {
+            composing = text.toString()
+            NativeBridge.imePreedit(SystemClock.uptimeMillis(), composing, composing.length, composing.length)
+            return true
+        }
+
+        override fun finishComposingText(): Boolean {
+            if (composing.isNotEmpty()) {
+                commitText(composing, 1)
+            }
+            return true
+        }
+
+        override fun commitText(text: CharSequence, newCursorPosition: Int): Boolean {
+            composing = ""
+            val now = SystemClock.uptimeMillis()
+            NativeBridge.imePreedit(now, "", -1, -1)
+            NativeBridge.imeCommit(now, text.toString())
+            return true
+        }
+
+        override fun deleteSurroundingText(beforeLength: Int, afterLength: Int): Boolean {
+            // nothing is mirrored on this side, let bevy handle it as backspace presses
+            repeat(beforeLength) {
+                val now = SystemClock.uptimeMillis()
+                sendKeyEvent(KeyEvent(now, now, KeyEvent.ACTION_DOWN, KeyEvent.KEYCODE_DEL, 0))
+                sendKeyEvent(KeyEvent(now, now, KeyEvent.ACTION_UP, KeyEvent.KEYCODE_DEL, 0))
+            }
+            return true
+        }
+    }
+}
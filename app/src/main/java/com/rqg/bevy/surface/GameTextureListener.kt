@@ -0,0 +1,40 @@
+package com.rqg.bevy.surface
+
+import android.graphics.PixelFormat
+import android.graphics.SurfaceTexture
+import android.view.Surface
+import android.view.TextureView
+
+/**
+ * Feeds the [SurfaceTexture] of a [TextureView] to bevy, for games in RecyclerView cells or
+ * animated transitions. A destroyed texture is released once the game let go of it, without
+ * blocking the UI thread like a `SurfaceView` does.
+ */
+class GameTextureListener(private val density: Float) : TextureView.SurfaceTextureListener {
+    private var surface: Surface? = null
+
+    override fun onSurfaceTextureAvailable(surfaceTexture: SurfaceTexture, width: Int, height: Int) {
+        val surface = Surface(surfaceTexture)
+        this.surface = surface
+        NativeBridge.surfaceCreated(surface)
+        // TextureView buffers are always RGBA_8888, composited like any other view
+        NativeBridge.surfaceChanged(width, height, PixelFormat.RGBA_8888, density)
+    }
+
+    override fun onSurfaceTextureSizeChanged(surfaceTexture: SurfaceTexture, width: Int, height: Int) {
+        NativeBridge.surfaceChanged(width, height, PixelFormat.RGBA_8888, density)
+    }
+
+    override fun onSurfaceTextureDestroyed(surfaceTexture: SurfaceTexture): Boolean {
+        val surface = surface ?: return true
+        this.surface = null
+        NativeBridge.surfaceTextureDestroyed {
+            surface.release()
+            surfaceTexture.release()
+        }
+        // released above, once the game is done with it
+        return false
+    }
+
+    override fun onSurfaceTextureUpdated(surfaceTexture: SurfaceTexture) {}
+}
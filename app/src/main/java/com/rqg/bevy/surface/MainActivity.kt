@@ -1,22 +1,32 @@
 package com.rqg.bevy.surface
 
 import android.annotation.SuppressLint
+import androidx.activity.OnBackPressedCallback
 import androidx.appcompat.app.AppCompatActivity
+import androidx.core.view.ViewCompat
+import androidx.core.view.WindowInsetsCompat
 import android.os.Bundle
 import android.os.Handler
+import android.os.SystemClock
+import android.graphics.PixelFormat
+import android.hardware.input.InputManager
 import android.util.Log
+import android.view.InputDevice
+import android.view.KeyEvent
 import android.view.MotionEvent
 import android.view.SurfaceHolder
-import android.view.SurfaceView
 import android.view.View
 import android.widget.Button
 
 class MainActivity : AppCompatActivity() {
     companion object {
         private const val TAG = "MainActivity"
+
+        // run at the display refresh rate instead of the default 60fps
+        private const val PACE_TO_DISPLAY = false
     }
 
-    private val mSurfaceView: SurfaceView by lazy {
+    private val mSurfaceView: GameSurfaceView by lazy {
         findViewById(R.id.surface_view)
     }
 
@@ -45,16 +55,46 @@ class MainActivity : AppCompatActivity() {
             }
         }
 
+        onBackPressedDispatcher.addCallback(this, backPressedCallback)
+
         mSurfaceView.holder.addCallback(surfaceCallback)
 
         mSurfaceView.setOnTouchListener(surfaceTouchListener)
+        // hover and scroll of mice and touchpads
+        mSurfaceView.setOnGenericMotionListener { _, event ->
+            if (gameStarted && event.isFromSource(InputDevice.SOURCE_CLASS_POINTER)) {
+                NativeBridge.motionEvent(event)
+                return@setOnGenericMotionListener true
+            }
+            false
+        }
+
+        // keep the game's HUD clear of notches and gesture bars
+        ViewCompat.setOnApplyWindowInsetsListener(mSurfaceView) { _, insets ->
+            val safeArea = insets.getInsets(WindowInsetsCompat.Type.systemBars() or WindowInsetsCompat.Type.displayCutout())
+            NativeBridge.setSafeAreaInsets(
+                safeArea.left.toFloat(), safeArea.top.toFloat(),
+                safeArea.right.toFloat(), safeArea.bottom.toFloat(),
+            )
+            insets
+        }
+
+        NativeBridge.imeListener = { enabled, _, _ ->
+            runOnUiThread { mSurfaceView.showIme(enabled) }
+        }
+        NativeBridge.surfaceFormatListener = { format ->
+            runOnUiThread { mSurfaceView.holder.setFormat(format) }
+        }
 
         NativeBridge.initialize(assets)
         NativeBridge.activityCreated(this)
+
+        getSystemService(InputManager::class.java).registerInputDeviceListener(inputDeviceListener, null)
     }
 
     override fun onDestroy() {
         super.onDestroy()
+        getSystemService(InputManager::class.java).unregisterInputDeviceListener(inputDeviceListener)
         NativeBridge.activityDestroyed()
     }
 
@@ -72,13 +112,21 @@ class MainActivity : AppCompatActivity() {
         }
         gameThread?.start()
 
+        connectGamepads()
+
+        if (PACE_TO_DISPLAY) {
+            mSurfaceView.display?.let { NativeBridge.setTargetFrameRate(it.refreshRate) }
+        }
+
         // Tell the native renderer that a surface has been created.
         if (mSurfaceView.holder.surface.isValid) {
             Log.d(TAG, "startGame: surface is valid")
             NativeBridge.surfaceCreated(mSurfaceView.holder.surface)
             val frame = mSurfaceView.holder.surfaceFrame
-            NativeBridge.surfaceChanged(frame.width(), frame.height())
+            NativeBridge.surfaceChanged(frame.width(), frame.height(), surfaceFormat, resources.displayMetrics.density)
         }
+        // the queue was drained, send the insets again
+        ViewCompat.requestApplyInsets(mSurfaceView)
     }
 
     fun stopGame() {
@@ -101,20 +149,91 @@ class MainActivity : AppCompatActivity() {
         NativeBridge.onPause()
     }
 
+    // ask the game first, fall back to the default back behaviour if it did not consume the press
+    private val backPressedCallback = object : OnBackPressedCallback(true) {
+        override fun handleOnBackPressed() {
+            if (gameStarted && NativeBridge.backPressed(SystemClock.uptimeMillis())) {
+                return
+            }
+            isEnabled = false
+            onBackPressedDispatcher.onBackPressed()
+            isEnabled = true
+        }
+    }
+
+    override fun dispatchKeyEvent(event: KeyEvent): Boolean {
+        if (gameStarted) {
+            if (isGamepad(event.device) && NativeBridge.isGamepadButton(event.keyCode)) {
+                if (event.repeatCount == 0) {
+                    NativeBridge.gamepadButton(event.eventTime, event.deviceId, event.keyCode, event.action == KeyEvent.ACTION_DOWN)
+                }
+                return true
+            }
+            // back reaches the game once, through the back press callback
+            if (event.keyCode != KeyEvent.KEYCODE_BACK) {
+                NativeBridge.keyEvent(event.eventTime, event.keyCode, event.action, event.metaState, event.getUnicodeChar(event.metaState))
+            }
+        }
+        return super.dispatchKeyEvent(event)
+    }
+
+    override fun dispatchGenericMotionEvent(event: MotionEvent): Boolean {
+        if (gameStarted && event.isFromSource(InputDevice.SOURCE_JOYSTICK) && event.action == MotionEvent.ACTION_MOVE) {
+            event.device?.motionRanges?.forEach {
+                NativeBridge.gamepadAxis(event.eventTime, event.deviceId, it.axis, event.getAxisValue(it.axis))
+            }
+            return true
+        }
+        return super.dispatchGenericMotionEvent(event)
+    }
+
+    private fun isGamepad(device: InputDevice?): Boolean {
+        val sources = device?.sources ?: return false
+        return sources and InputDevice.SOURCE_GAMEPAD == InputDevice.SOURCE_GAMEPAD ||
+                sources and InputDevice.SOURCE_JOYSTICK == InputDevice.SOURCE_JOYSTICK
+    }
+
+    private fun connectGamepads() {
+        InputDevice.getDeviceIds()
+            .mapNotNull { InputDevice.getDevice(it) }
+            .filter { isGamepad(it) }
+            .forEach { NativeBridge.gamepadConnected(SystemClock.uptimeMillis(), it.id, it.name) }
+    }
+
+    private val inputDeviceListener = object : InputManager.InputDeviceListener {
+        override fun onInputDeviceAdded(deviceId: Int) {
+            val device = InputDevice.getDevice(deviceId)
+            if (gameStarted && isGamepad(device)) {
+                NativeBridge.gamepadConnected(SystemClock.uptimeMillis(), deviceId, device!!.name)
+            }
+        }
+
+        override fun onInputDeviceRemoved(deviceId: Int) {
+            if (gameStarted) {
+                NativeBridge.gamepadDisconnected(SystemClock.uptimeMillis(), deviceId)
+            }
+        }
+
+        override fun onInputDeviceChanged(deviceId: Int) {}
+    }
+
 
     @SuppressLint("ClickableViewAccessibility")
     private val surfaceTouchListener = View.OnTouchListener { _, event ->
-
-        val pointerId = event.getPointerId(event.actionIndex)
-        val action = event.actionMasked
-        MotionEvent.ACTION_DOWN
-        NativeBridge.touchEvent(pointerId, action, event.x, event.y)
+        // let gestures the game does not want fall through to the views below
+        if (event.actionMasked == MotionEvent.ACTION_DOWN && !NativeBridge.hitTest(event.x, event.y)) {
+            return@OnTouchListener false
+        }
+        NativeBridge.motionEvent(event)
         return@OnTouchListener true
     }
 
+    // the holder does not expose it, remember the last one reported
+    private var surfaceFormat = PixelFormat.RGB_565
+
     private val surfaceCallback = object : SurfaceHolder.Callback2 {
         override fun surfaceRedrawNeeded(holder: SurfaceHolder) {
-            // Tell the native renderer that the surface has been redrawn.
+            // The surface content must be drawn before this returns, render a frame synchronously.
             NativeBridge.surfaceRedrawNeeded()
         }
 
@@ -125,7 +244,8 @@ class MainActivity : AppCompatActivity() {
 
         override fun surfaceChanged(holder: SurfaceHolder, format: Int, width: Int, height: Int) {
             // Tell the native renderer that the surface has changed.
-            NativeBridge.surfaceChanged(width, height)
+            surfaceFormat = format
+            NativeBridge.surfaceChanged(width, height, format, resources.displayMetrics.density)
         }
 
         override fun surfaceDestroyed(holder: SurfaceHolder) {
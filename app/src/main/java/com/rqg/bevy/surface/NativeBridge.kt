@@ -2,7 +2,14 @@ package com.rqg.bevy.surface
 
 import android.app.Activity
 import android.content.res.AssetManager
+import android.os.Handler
+import android.os.Looper
+import android.util.Log
+import android.view.InputDevice
+import android.view.MotionEvent
 import android.view.Surface
+import java.util.concurrent.ConcurrentHashMap
+import java.util.concurrent.atomic.AtomicLong
 
 /**
  * * Created by rqg on 2023/11/23.
@@ -16,25 +23,247 @@ class NativeBridge {
 
 
     companion object {
-        external fun surfaceRedrawNeeded();
+        private const val TAG = "NativeBridge"
 
-        external fun surfaceCreated(surface: Surface)
+        // error codes returned by the native functions and passed to onNativeError, 0 means success.
+        const val ERROR_NOT_INITIALIZED = 1
+        const val ERROR_NULL_POINTER = 2
+        const val ERROR_UNKNOWN_MOTION_ACTION = 3
+        const val ERROR_INVALID_TRANSITION = 4
+        const val ERROR_NO_PRIMARY_WINDOW = 5
+        const val ERROR_TIMEOUT = 6
+        const val ERROR_MALFORMED_TOUCH_DATA = 7
+        const val ERROR_UNKNOWN_KEY_ACTION = 8
 
-        external fun surfaceChanged(width: Int, height: Int)
+        // floats per pointer passed to touchEvents: id, x, y, pressure, size, tool type, tilt
+        private const val POINTER_STRIDE = 7
 
-        external fun surfaceDestroyed()
+        /**
+         * Called with errors reported by the game thread, on the thread that reported them.
+         */
+        var errorListener: ((code: Int, message: String) -> Unit)? = null
+
+        @JvmStatic
+        fun onNativeError(code: Int, message: String) {
+            Log.e(TAG, "onNativeError: $code $message")
+            errorListener?.invoke(code, message)
+        }
+
+        /**
+         * Called on the game thread when bevy wants the soft keyboard shown or hidden. [x], [y] is
+         * where the text is entered, in pixels.
+         */
+        var imeListener: ((enabled: Boolean, x: Float, y: Float) -> Unit)? = null
+
+        @JvmStatic
+        fun onImeRequest(enabled: Boolean, x: Float, y: Float) {
+            imeListener?.invoke(enabled, x, y)
+        }
+
+        /**
+         * Text being composed by the soft keyboard, the cursor is in UTF-16 offsets, -1 for none.
+         * [eventTime] is in `SystemClock.uptimeMillis` like the other input functions.
+         */
+        external fun imePreedit(eventTime: Long, text: String, cursorStart: Int, cursorEnd: Int): Int
+
+        external fun imeCommit(eventTime: Long, text: String): Int
+
+        /**
+         * Renders a frame and returns once it is done.
+         */
+        external fun surfaceRedrawNeeded(): Int
+
+        external fun surfaceCreated(surface: Surface): Int
+
+        /**
+         * [width] and [height] are in pixels, [format] is the `PixelFormat` of the surface,
+         * [density] is `DisplayMetrics.density`.
+         */
+        external fun surfaceChanged(width: Int, height: Int, format: Int, density: Float): Int
+
+        /**
+         * Called on the game thread when the game wants surface buffers in another `PixelFormat`,
+         * pass it to `SurfaceHolder.setFormat`. `PixelFormat.TRANSLUCENT` means the game is drawn
+         * over the views under the surface, which also needs `SurfaceView.setZOrderOnTop` before
+         * the view is attached.
+         */
+        var surfaceFormatListener: ((format: Int) -> Unit)? = null
+
+        @JvmStatic
+        fun onSurfaceFormatRequest(format: Int) {
+            surfaceFormatListener?.invoke(format)
+        }
+
+        /**
+         * Blocks until the game let go of the surface, as `SurfaceHolder.Callback.surfaceDestroyed`
+         * needs.
+         */
+        external fun surfaceDestroyed(): Int
+
+        private val mainHandler = Handler(Looper.getMainLooper())
+        private val nextReleaseId = AtomicLong()
+        private val pendingReleases = ConcurrentHashMap<Long, () -> Unit>()
+
+        /**
+         * Like [surfaceDestroyed] but returns right away, [onReleased] runs on the main thread once
+         * the game let go of the surface. For `TextureView`s, whose `SurfaceTexture` may be released
+         * after `onSurfaceTextureDestroyed` returned false.
+         */
+        fun surfaceTextureDestroyed(onReleased: () -> Unit): Int {
+            val releaseId = nextReleaseId.incrementAndGet()
+            pendingReleases[releaseId] = onReleased
+            return surfaceTextureDestroyed(releaseId)
+        }
+
+        private external fun surfaceTextureDestroyed(releaseId: Long): Int
+
+        @JvmStatic
+        fun onSurfaceReleased(releaseId: Long) {
+            pendingReleases.remove(releaseId)?.let { mainHandler.post(it) }
+        }
+
+        /**
+         * The display cutout and system bar insets, in pixels.
+         */
+        external fun setSafeAreaInsets(left: Float, top: Float, right: Float, bottom: Float): Int
 
         external fun runGameLoop()
 
-        external fun stopGame()
+        external fun stopGame(): Int
+
+        /**
+         * Whether a touch at [x], [y] hits the game, see `TouchHitTest` on the bevy side.
+         */
+        external fun hitTest(x: Float, y: Float): Boolean
+
+        /**
+         * Mice and touchpads, whatever tool type they report (touchpads report fingers). A stylus
+         * hovering over a tablet can come as a mouse too, it still touches.
+         */
+        private fun isMouse(event: MotionEvent): Boolean {
+            val toolType = event.getToolType(0)
+            if (toolType == MotionEvent.TOOL_TYPE_STYLUS || toolType == MotionEvent.TOOL_TYPE_ERASER) {
+                return false
+            }
+            return event.isFromSource(InputDevice.SOURCE_MOUSE) || event.isFromSource(InputDevice.SOURCE_TOUCHPAD)
+        }
+
+        /**
+         * The input functions take the `eventTime` of their event, so the game can tell how long
+         * the input waited.
+         */
+        external fun mouseEvent(
+            eventTime: Long, action: Int, actionButton: Int, x: Float, y: Float,
+            dx: Float, dy: Float, scrollX: Float, scrollY: Float,
+        ): Int
+
+        /**
+         * [data] holds `historySize + 1` samples, oldest first, of [pointerCount] pointers each.
+         * Use [motionEvent] to pack a [MotionEvent].
+         */
+        external fun touchEvents(
+            action: Int, actionIndex: Int, pointerCount: Int, historySize: Int,
+            data: FloatArray, eventTimes: LongArray,
+        ): Int
+
+        /**
+         * Forwards every pointer of [event], including its historical samples. Mouse and touchpad
+         * events move the cursor instead of touching.
+         */
+        fun motionEvent(event: MotionEvent): Int {
+            if (isMouse(event)) {
+                return mouseEvent(
+                    event.eventTime, event.actionMasked, event.actionButton, event.x, event.y,
+                    event.getAxisValue(MotionEvent.AXIS_RELATIVE_X),
+                    event.getAxisValue(MotionEvent.AXIS_RELATIVE_Y),
+                    event.getAxisValue(MotionEvent.AXIS_HSCROLL),
+                    event.getAxisValue(MotionEvent.AXIS_VSCROLL),
+                )
+            }
+
+            val pointerCount = event.pointerCount
+            val historySize = event.historySize
+            val data = FloatArray((historySize + 1) * pointerCount * POINTER_STRIDE)
+            val eventTimes = LongArray(historySize + 1) {
+                if (it == historySize) event.eventTime else event.getHistoricalEventTime(it)
+            }
+
+            var i = 0
+            for (h in 0..historySize) {
+                for (p in 0 until pointerCount) {
+                    val current = h == historySize
+                    data[i++] = event.getPointerId(p).toFloat()
+                    data[i++] = if (current) event.getX(p) else event.getHistoricalX(p, h)
+                    data[i++] = if (current) event.getY(p) else event.getHistoricalY(p, h)
+                    data[i++] = if (current) event.getPressure(p) else event.getHistoricalPressure(p, h)
+                    data[i++] = if (current) event.getSize(p) else event.getHistoricalSize(p, h)
+                    data[i++] = event.getToolType(p).toFloat()
+                    data[i++] = if (current) event.getAxisValue(MotionEvent.AXIS_TILT, p)
+                    else event.getHistoricalAxisValue(MotionEvent.AXIS_TILT, p, h)
+                }
+            }
+
+            return touchEvents(event.actionMasked, event.actionIndex, pointerCount, historySize, data, eventTimes)
+        }
+
+        /**
+         * [unicode] is `KeyEvent.getUnicodeChar(metaState)`, 0 if the key types nothing.
+         */
+        external fun keyEvent(eventTime: Long, keycode: Int, action: Int, metaState: Int, unicode: Int): Int
+
+        external fun gamepadConnected(eventTime: Long, deviceId: Int, name: String): Int
+
+        external fun gamepadDisconnected(eventTime: Long, deviceId: Int): Int
+
+        /**
+         * Whether [keycode] should go to [gamepadButton] instead of [keyEvent].
+         */
+        external fun isGamepadButton(keycode: Int): Boolean
+
+        external fun gamepadButton(eventTime: Long, deviceId: Int, keycode: Int, pressed: Boolean): Int
+
+        /**
+         * [axis] is one of the `MotionEvent.AXIS_*` values.
+         */
+        external fun gamepadAxis(eventTime: Long, deviceId: Int, axis: Int, value: Float): Int
+
+        /**
+         * Sends the back press to the game and returns whether it consumed it. Blocks for at most
+         * 200ms.
+         */
+        external fun backPressed(eventTime: Long): Boolean
+
+        external fun onResume(): Int
+
+        external fun onPause(): Int
+
+        external fun initialize(assetManager: AssetManager): Int
+
+        /**
+         * Caps the frame rate, `fps <= 0` lets frames be paced by vsync only.
+         */
+        external fun setTargetFrameRate(fps: Float): Int
 
-        external fun touchEvent(pointerId: Int, acton: Int, x: Float, y: Float)
+        /**
+         * Frame rate used once no input arrived for [idleAfterMs], `fps <= 0` disables it.
+         */
+        external fun setIdleFrameRate(fps: Float, idleAfterMs: Long): Int
 
-        external fun onResume()
+        /**
+         * In reactive mode bevy only updates on input, redraw requests or every [maxWaitMs].
+         */
+        external fun setUpdateMode(reactive: Boolean, maxWaitMs: Long): Int
 
-        external fun onPause()
+        /**
+         * Renders at [width] x [height] pixels and lets the compositor scale it to the surface,
+         * `0` x `0` renders at the surface size. Touches stay in surface pixels.
+         */
+        external fun setRenderResolution(width: Int, height: Int): Int
 
-        external fun initialize(assetManager: AssetManager)
+        /**
+         * Renders at [scale] times the surface size per side, e.g. `0.5f` on low-end devices.
+         */
+        external fun setRenderScale(scale: Float): Int
 
         external fun drainCommandQueue()
 
@@ -6,8 +6,11 @@ use bevy::asset::BoxedFuture;
 use bevy::asset::io::{AssetReader, AssetReaderError, AssetSource, AssetSourceId, PathStream, Reader, VecReader};
 use bevy::prelude::*;
 use bevy::tasks::futures_lite::Stream;
-use crate::get_asset_manager;
+use ndk::asset::AssetManager;
 
+use crate::command::CMD_QUEUE;
+
+/// Registers the android `AssetManager` as the default bevy asset source.
 pub struct AndroidAssetReaderPlugin;
 
 impl Plugin for AndroidAssetReaderPlugin {
@@ -25,6 +28,22 @@ impl Plugin for AndroidAssetReaderPlugin {
 }
 
 
+/// Returns the `AssetManager` passed to `initialize`, if any.
+pub fn get_asset_manager() -> Option<ndk::asset::AssetManager> {
+    let asset_manager = CMD_QUEUE.get().unwrap()
+        .asset_manager.lock().unwrap();
+    match *asset_manager {
+        None => {
+            None
+        }
+        Some(ref asset_manager) => {
+            unsafe {
+                Some(AssetManager::from_ptr(asset_manager.ptr()))
+            }
+        }
+    }
+}
+
 struct CustomAssetReader(Box<dyn AssetReader>);
 
 impl AssetReader for CustomAssetReader {
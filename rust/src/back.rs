@@ -0,0 +1,10 @@
+use bevy::prelude::*;
+
+/// The android back button (or gesture) was used. Set [`BackPressConsumed`] in the same update to
+/// keep the host from handling it, e.g. when closing a popup instead of leaving the game.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackPressed;
+
+/// Whether the last [`BackPressed`] was consumed by the game. Reset before each press.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BackPressConsumed(pub bool);
@@ -1,20 +1,56 @@
-use std::ffi::{CStr, CString};
+//! The C entry points called from the JNI glue in `native-lib.cpp`.
+//!
+//! They are `#[no_mangle]`, so a game crate depending on this crate exports them from its own
+//! `cdylib` without any wrapper.
+
+use std::ffi::{c_char, CStr, CString};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::os::fd::{FromRawFd, RawFd};
 use std::ptr::NonNull;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use bevy::input::touch::TouchPhase;
-use bevy::log::{info, Level};
+use bevy::input::ButtonState;
+use bevy::log::Level;
 use bevy::math::vec2;
-use bevy::prelude::TouchInput;
 use jni_sys::{JavaVM, jobject};
 use ndk::asset::AssetManager;
-use ndk::event::MotionAction;
 use ndk::native_window::NativeWindow;
 use ndk_sys::{AAssetManager, ANativeWindow};
 
-use crate::{Cmd, CMD_QUEUE, init_command_queue, run_game_loop};
+use crate::command::{Cmd, CMD_QUEUE, drain_command_queue, global_queue, init_command_queue, SurfaceRelease};
+use crate::error::{BridgeError, ErrorCallback, result_code, set_error_callback};
+use crate::format::{set_surface_format_callback, SurfaceFormat, SurfaceFormatCallback};
+use crate::gamepad;
+use crate::ime::{ImeCallback, set_ime_callback, utf16_to_byte_index};
+use crate::keyboard::{key_code, key_state, received_character};
+use crate::mouse::mouse_action;
+use crate::pacing::{FrameRate, UpdateMode};
+use crate::resolution::RenderResolution;
+use crate::run_game_loop;
+use crate::safe_area::SafeAreaInsets;
+use crate::surface::AndroidSurface;
+use crate::touch::unpack_motion_event;
+
+/// How long `surfaceRedrawNeeded` waits for the frame.
+pub const REDRAW_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long `backPressed` waits for the game to answer.
+pub const BACK_PRESSED_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// The [`Instant`] of an android event time, `SystemClock.uptimeMillis` based like
+/// `InputEvent.getEventTime` which the input exports take as `event_time`.
+fn uptime_to_instant(uptime_millis: i64) -> Instant {
+    let mut now = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // uptimeMillis is CLOCK_MONOTONIC
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+    let now_millis = now.tv_sec as i64 * 1000 + now.tv_nsec as i64 / 1_000_000;
+
+    let age = Duration::from_millis(now_millis.saturating_sub(uptime_millis).max(0) as u64);
+    let now = Instant::now();
+    now.checked_sub(age).unwrap_or(now)
+}
 
 fn forward_stdio_to_logcat() {
     // XXX: make this stdout/stderr redirection an optional / opt-in feature?...
@@ -67,32 +103,60 @@ fn android_log(level: Level, tag: &CStr, msg: &CStr) {
     }
 }
 
+/// Must be called before any other function. Returns `0` or a [`BridgeError`] code.
 #[no_mangle]
 pub extern "C" fn initialize(
     asset_manager: *mut AAssetManager,
-) {
+) -> i32 {
     forward_stdio_to_logcat();
     init_command_queue();
 
+    let Some(asset_manager) = NonNull::new(asset_manager) else {
+        return BridgeError::NullPointer.code();
+    };
+
     unsafe {
         let mut am = CMD_QUEUE.get().unwrap()
             .asset_manager.lock().unwrap();
-        *am = Some(AssetManager::from_ptr(NonNull::new(asset_manager).unwrap()));
+        *am = Some(AssetManager::from_ptr(asset_manager));
     }
+    0
+}
+
+/// Sets the callback errors on the game thread are reported to, null removes it.
+#[no_mangle]
+pub extern "C" fn setErrorCallback(callback: Option<ErrorCallback>) {
+    set_error_callback(callback);
 }
 
 #[no_mangle]
 pub extern "C" fn drainCommandQueue() {
-    let rx = CMD_QUEUE.get().unwrap()
-        .receiver.lock().unwrap();
-    while let Ok(_) = rx.try_recv() {
-        // ignore
-    }
+    drain_command_queue();
+}
+
+/// Renders a frame and returns once it is done, as `SurfaceHolder.Callback2` requires. Gives up
+/// after [`REDRAW_TIMEOUT`] so a stuck game loop does not freeze the UI thread.
+#[no_mangle]
+pub extern "C" fn surfaceRedrawNeeded() -> i32 {
+    result_code(global_queue().and_then(|cmd_queue| cmd_queue.redraw(REDRAW_TIMEOUT)))
 }
 
+/// Lets the game handle the back button pressed at `event_time`. `consumed` is set to whether it
+/// did, the host should handle the press itself otherwise (also when an error is returned).
 #[no_mangle]
-pub extern "C" fn surfaceRedrawNeeded() {
-    //ignore this
+pub extern "C" fn backPressed(event_time: i64, consumed: *mut bool) -> i32 {
+    let Some(consumed) = (unsafe { consumed.as_mut() }) else {
+        return BridgeError::NullPointer.code();
+    };
+
+    *consumed = false;
+    match global_queue().and_then(|cmd_queue| cmd_queue.back_pressed(uptime_to_instant(event_time), BACK_PRESSED_TIMEOUT)) {
+        Ok(result) => {
+            *consumed = result;
+            0
+        }
+        Err(err) => err.code(),
+    }
 }
 
 #[no_mangle]
@@ -118,86 +182,315 @@ pub extern "C" fn activityDestroyed() {
 #[no_mangle]
 pub extern "C" fn surfaceCreated(
     window: *mut ANativeWindow,
-) {
+) -> i32 {
+    let Some(window) = NonNull::new(window) else {
+        return BridgeError::NullPointer.code();
+    };
+
     unsafe {
-        let window = NativeWindow::from_ptr(NonNull::new(window).unwrap());
-        Cmd::SurfaceCreated(window).send();
+        let window = NativeWindow::from_ptr(window);
+        result_code(Cmd::SurfaceCreated(Box::new(AndroidSurface::from(window))).send())
     }
 }
 
 
+/// `format` is the `PixelFormat` passed to `SurfaceHolder.Callback.surfaceChanged`. `density` is
+/// `DisplayMetrics.density`, it becomes the window's scale factor. Values `<= 0` mean `1.0`.
 #[no_mangle]
 pub extern "C" fn surfaceChanged(
-    width: u32, height: u32,
-) {
-    Cmd::SurfaceChanged(width, height).send();
+    width: u32, height: u32, format: i32, density: f32,
+) -> i32 {
+    let scale_factor = if density > 0. { density as f64 } else { 1. };
+    result_code(Cmd::SurfaceChanged {
+        width,
+        height,
+        scale_factor,
+        format: SurfaceFormat::from_android(format),
+    }.send())
+}
+
+#[no_mangle]
+pub extern "C" fn setSurfaceFormatCallback(callback: Option<SurfaceFormatCallback>) {
+    set_surface_format_callback(callback);
 }
 
 
 #[no_mangle]
-pub extern "C" fn surfaceDestroyed() {
-    let running_loop = CMD_QUEUE.get().unwrap()
-        .running_loop.lock().unwrap();
+pub extern "C" fn surfaceDestroyed() -> i32 {
+    result_code(global_queue().map(|cmd_queue| cmd_queue.destroy_surface()))
+}
+
+/// Called with the id passed to [`surfaceTextureDestroyed`] once the surface can be released, on
+/// the game thread, or on the caller's if no game loop holds the surface.
+pub type SurfaceReleasedCallback = extern "C" fn(release_id: i64);
 
-    if !*running_loop {
-        return;
+static SURFACE_RELEASED_CALLBACK: Mutex<Option<SurfaceReleasedCallback>> = Mutex::new(None);
+
+#[no_mangle]
+pub extern "C" fn setSurfaceReleasedCallback(callback: Option<SurfaceReleasedCallback>) {
+    *SURFACE_RELEASED_CALLBACK.lock().unwrap() = callback;
+}
+
+/// Like [`surfaceDestroyed`] but returns right away, for `TextureView`s whose `SurfaceTexture`
+/// outlives `onSurfaceTextureDestroyed`. The [`SurfaceReleasedCallback`] is called with
+/// `release_id` once the game let go of the surface, also if this fails.
+#[no_mangle]
+pub extern "C" fn surfaceTextureDestroyed(release_id: i64) -> i32 {
+    let release = SurfaceRelease::new(move || {
+        let surface_released_callback = *SURFACE_RELEASED_CALLBACK.lock().unwrap();
+        if let Some(surface_released_callback) = surface_released_callback {
+            surface_released_callback(release_id);
+        }
+    });
+    result_code(global_queue().map(|cmd_queue| cmd_queue.destroy_surface_async(release)))
+}
+
+#[no_mangle]
+pub extern "C" fn runGameLoop() {
+    run_game_loop();
+}
+
+#[no_mangle]
+pub extern "C" fn stopGame() -> i32 {
+    result_code(Cmd::StopGame.send())
+}
+
+/// Whether a touch at `x`, `y` (in pixels) hits the game. Ask when a gesture starts and let it
+/// fall through to the views below the surface if it does not.
+#[no_mangle]
+pub extern "C" fn hitTest(x: f32, y: f32) -> bool {
+    global_queue().is_ok_and(|cmd_queue| cmd_queue.hit_test(vec2(x, y)))
+}
+
+/// Forwards every pointer of a `MotionEvent`, including its historical samples. `data` is laid out
+/// as described in [`unpack_motion_event`], `event_times` holds the sample times, oldest first, from
+/// `getHistoricalEventTime` and `getEventTime`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn touchEvents(
+    action: i32,
+    action_index: i32,
+    pointer_count: i32,
+    history_size: i32,
+    data: *const f32,
+    len: usize,
+    event_times: *const i64,
+    times_len: usize,
+) -> i32 {
+    if data.is_null() || event_times.is_null() {
+        return BridgeError::NullPointer.code();
+    }
+    let data = unsafe { std::slice::from_raw_parts(data, len) };
+    let times: Vec<_> = unsafe { std::slice::from_raw_parts(event_times, times_len) }
+        .iter()
+        .map(|&event_time| uptime_to_instant(event_time))
+        .collect();
+
+    let samples = unpack_motion_event(
+        action,
+        action_index.max(0) as usize,
+        pointer_count.max(0) as usize,
+        history_size.max(0) as usize,
+        data,
+        &times,
+    );
+    match samples {
+        Ok(samples) if samples.is_empty() => 0,
+        Ok(samples) => result_code(Cmd::TouchEvents(samples).send()),
+        Err(err) => err.code(),
     }
+}
 
-    let mut done = CMD_QUEUE.get().unwrap()
-        .surface_destroyed_handle_done.lock().unwrap();
-    *done = false;
+#[no_mangle]
+pub extern "C" fn setImeCallback(callback: Option<ImeCallback>) {
+    set_ime_callback(callback);
+}
 
-    Cmd::SurfaceDestroyed.send();
+/// The soft keyboard is composing `text`. `cursor_start`/`cursor_end` are UTF-16 offsets, negative
+/// if there is no cursor.
+#[no_mangle]
+pub extern "C" fn imePreedit(event_time: i64, text: *const c_char, cursor_start: i32, cursor_end: i32) -> i32 {
+    if text.is_null() {
+        return BridgeError::NullPointer.code();
+    }
+    let value = unsafe { CStr::from_ptr(text) }.to_string_lossy().into_owned();
+
+    let cursor = (cursor_start >= 0 && cursor_end >= 0).then(|| (
+        utf16_to_byte_index(&value, cursor_start as usize),
+        utf16_to_byte_index(&value, cursor_end as usize),
+    ));
+    result_code(Cmd::ImePreedit { value, cursor, time: uptime_to_instant(event_time) }.send())
+}
 
-    while !*done {
-        // wait for surfaceDestroyed to be handled
-        info!("wait for surfaceDestroyed to be handled");
-        done = CMD_QUEUE.get().unwrap()
-            .surface_destroyed_handle_done_var.wait(done).unwrap();
+/// The soft keyboard committed `text`.
+#[no_mangle]
+pub extern "C" fn imeCommit(event_time: i64, text: *const c_char) -> i32 {
+    if text.is_null() {
+        return BridgeError::NullPointer.code();
     }
+    let value = unsafe { CStr::from_ptr(text) }.to_string_lossy().into_owned();
 
-    info!("surfaceDestroyed handled");
+    result_code(Cmd::ImeCommit { value, time: uptime_to_instant(event_time) }.send())
 }
 
+/// Forwards a `MotionEvent` from a mouse or touchpad. `dx`/`dy` are `AXIS_RELATIVE_X`/`Y`,
+/// `scroll_x`/`scroll_y` are `AXIS_HSCROLL`/`AXIS_VSCROLL`.
 #[no_mangle]
-pub extern "C" fn runGameLoop() {
-    run_game_loop();
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn mouseEvent(
+    event_time: i64,
+    action: i32,
+    action_button: i32,
+    x: f32,
+    y: f32,
+    dx: f32,
+    dy: f32,
+    scroll_x: f32,
+    scroll_y: f32,
+) -> i32 {
+    match mouse_action(action, action_button, vec2(dx, dy), vec2(scroll_x, scroll_y)) {
+        Ok(Some(action)) => result_code(Cmd::MouseEvent {
+            position: vec2(x, y),
+            action,
+            time: uptime_to_instant(event_time),
+        }.send()),
+        Ok(None) => 0,
+        Err(err) => err.code(),
+    }
 }
 
+/// Forwards a hardware key. `unicode` is `KeyEvent.getUnicodeChar(metaState)`, `0` if the key
+/// types nothing. The android keycode doubles as the scan code.
 #[no_mangle]
-pub extern "C" fn stopGame() {
-    Cmd::StopGame.send();
+pub extern "C" fn keyEvent(event_time: i64, keycode: i32, action: i32, meta_state: i32, unicode: i32) -> i32 {
+    let state = match key_state(action) {
+        Ok(state) => state,
+        Err(err) => return err.code(),
+    };
+
+    result_code(Cmd::KeyEvent {
+        scan_code: keycode as u32,
+        key_code: key_code(keycode),
+        state,
+        character: (state == ButtonState::Pressed)
+            .then(|| received_character(unicode, meta_state))
+            .flatten(),
+        time: uptime_to_instant(event_time),
+    }.send())
 }
 
+/// A game controller was connected, `name` is its `InputDevice.getName`.
 #[no_mangle]
-pub extern "C" fn touchEvent(pointer_id: i32, action: i32, x: f32, y: f32) {
-    let action = (action as u32).try_into().unwrap();
-    let phase = match action {
-        MotionAction::PointerDown | MotionAction::Down => Some(TouchPhase::Started),
-        MotionAction::Up | MotionAction::PointerUp => Some(TouchPhase::Ended),
-        MotionAction::Move => Some(TouchPhase::Moved),
-        MotionAction::Cancel => Some(TouchPhase::Canceled),
-        _ => { None }
+pub extern "C" fn gamepadConnected(event_time: i64, device_id: i32, name: *const c_char) -> i32 {
+    if name.is_null() {
+        return BridgeError::NullPointer.code();
+    }
+    let name = unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned();
+
+    result_code(Cmd::GamepadEvents {
+        events: vec![gamepad::connection_event(device_id, name)],
+        time: uptime_to_instant(event_time),
+    }.send())
+}
+
+#[no_mangle]
+pub extern "C" fn gamepadDisconnected(event_time: i64, device_id: i32) -> i32 {
+    result_code(Cmd::GamepadEvents {
+        events: vec![gamepad::disconnection_event(device_id)],
+        time: uptime_to_instant(event_time),
+    }.send())
+}
+
+/// Returns whether `keycode` is a gamepad button, so the host can send other keys to `keyEvent`.
+#[no_mangle]
+pub extern "C" fn isGamepadButton(keycode: i32) -> bool {
+    gamepad::button_type(keycode).is_some()
+}
+
+/// Forwards a controller button. Keys that are not gamepad buttons are ignored.
+#[no_mangle]
+pub extern "C" fn gamepadButton(event_time: i64, device_id: i32, keycode: i32, pressed: bool) -> i32 {
+    let Some(button_type) = gamepad::button_type(keycode) else {
+        return 0;
     };
 
-    if let Some(phase) = phase {
-        Cmd::TouchEvent(TouchInput {
-            phase,
-            position: vec2(x, y),
-            force: None,
-            id: pointer_id as u64,
-        }).send();
+    result_code(Cmd::GamepadEvents {
+        events: vec![gamepad::button_event(device_id, button_type, pressed)],
+        time: uptime_to_instant(event_time),
+    }.send())
+}
+
+/// Forwards the value of a `MotionEvent` axis of a controller.
+#[no_mangle]
+pub extern "C" fn gamepadAxis(event_time: i64, device_id: i32, axis: i32, value: f32) -> i32 {
+    let events = gamepad::axis_events(device_id, axis, value);
+    if events.is_empty() {
+        return 0;
     }
+
+    result_code(Cmd::GamepadEvents { events, time: uptime_to_instant(event_time) }.send())
+}
+
+/// The parts of the window covered by the display cutout and the system bars, in pixels, from
+/// `WindowInsets.getInsets(systemBars() | displayCutout())`.
+#[no_mangle]
+pub extern "C" fn setSafeAreaInsets(left: f32, top: f32, right: f32, bottom: f32) -> i32 {
+    result_code(Cmd::SafeAreaInsets(SafeAreaInsets { left, top, right, bottom }).send())
+}
+
+#[no_mangle]
+pub extern "C" fn onResume() -> i32 {
+    result_code(Cmd::OnResume.send())
 }
 
 #[no_mangle]
-pub extern "C" fn onResume() {
-    Cmd::OnResume.send();
+pub extern "C" fn onPause() -> i32 {
+    result_code(Cmd::OnPause.send())
 }
 
+/// Sets the target frame rate, `fps <= 0` removes the cap so frames are paced by vsync.
 #[no_mangle]
-pub extern "C" fn onPause() {
-    Cmd::OnPause.send();
+pub extern "C" fn setTargetFrameRate(fps: f32) -> i32 {
+    result_code(Cmd::SetTargetFrameRate(FrameRate::from_fps(fps)).send())
 }
 
+/// Sets the frame rate used once no input arrived for `idle_after_ms`, `fps <= 0` disables it.
+#[no_mangle]
+pub extern "C" fn setIdleFrameRate(fps: f32, idle_after_ms: i64) -> i32 {
+    let rate = (fps > 0.).then(|| FrameRate::from_fps(fps));
+    let idle_after = Duration::from_millis(idle_after_ms.max(0) as u64);
+    result_code(Cmd::SetIdleFrameRate { rate, idle_after }.send())
+}
 
+/// Switches between updating every frame and only when needed, at least every `max_wait_ms`.
+#[no_mangle]
+pub extern "C" fn setUpdateMode(reactive: bool, max_wait_ms: i64) -> i32 {
+    let update_mode = if reactive {
+        UpdateMode::Reactive { max_wait: Duration::from_millis(max_wait_ms.max(0) as u64) }
+    } else {
+        UpdateMode::Continuous
+    };
+    result_code(Cmd::SetUpdateMode(update_mode).send())
+}
+
+/// Renders at `width` x `height` pixels, scaled to the surface by the compositor. A `0` side
+/// follows the aspect ratio of the surface, `0` x `0` renders at the surface size again.
+#[no_mangle]
+pub extern "C" fn setRenderResolution(width: u32, height: u32) -> i32 {
+    let render_resolution = match (width, height) {
+        (0, 0) => RenderResolution::Native,
+        (width, height) => RenderResolution::Fixed { width, height },
+    };
+    result_code(Cmd::SetRenderResolution(render_resolution).send())
+}
+
+/// Renders at `scale` times the surface size per side, `scale <= 0` or `1` renders at the surface
+/// size again.
+#[no_mangle]
+pub extern "C" fn setRenderScale(scale: f32) -> i32 {
+    let render_resolution = match scale > 0. && scale != 1. {
+        true => RenderResolution::Scale(scale),
+        false => RenderResolution::Native,
+    };
+    result_code(Cmd::SetRenderResolution(render_resolution).send())
+}
@@ -0,0 +1,342 @@
+use std::sync::{Arc, Condvar, mpsc, Mutex, OnceLock, RwLock};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use bevy::input::ButtonState;
+use bevy::input::gamepad::GamepadEvent;
+use bevy::prelude::*;
+
+use crate::error::BridgeError;
+use crate::format::SurfaceFormat;
+use crate::hit_test::HitRegions;
+use crate::mouse::MouseAction;
+use crate::pacing::{FrameRate, UpdateMode};
+use crate::resolution::RenderResolution;
+use crate::safe_area::SafeAreaInsets;
+use crate::surface::Surface;
+use crate::touch::TouchSample;
+
+/// Commands sent from the host (usually the android UI thread) to the game loop.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Cmd {
+    SurfaceCreated(Box<dyn Surface>),
+    /// The size in physical pixels, the display density (the window's backend scale factor, scaled
+    /// down with the [`RenderResolution`]), and the pixel format of the buffers. Only the first
+    /// size of a surface is applied right away, see
+    /// [`ResizeDebounce`](crate::lifecycle::ResizeDebounce).
+    SurfaceChanged {
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+        format: SurfaceFormat,
+    },
+    /// The surface is going away. With `None` the host blocks in
+    /// [`destroy_surface`](CmdQueue::destroy_surface) until it is released, otherwise the
+    /// [`SurfaceRelease`] tells it.
+    SurfaceDestroyed(Option<SurfaceRelease>),
+    StopGame,
+    /// Every pointer of a `MotionEvent`, in the order they happened.
+    TouchEvents(Vec<TouchSample>),
+    /// A mouse or touchpad event, the cursor is moved to `position` (in physical pixels) first.
+    MouseEvent {
+        position: Vec2,
+        action: MouseAction,
+        time: Instant,
+    },
+    /// The text being composed by the soft keyboard, sent as [`Ime::Preedit`](bevy::window::Ime::Preedit).
+    /// `cursor` is a byte range in `value`.
+    ImePreedit {
+        value: String,
+        cursor: Option<(usize, usize)>,
+        time: Instant,
+    },
+    /// Text committed by the soft keyboard, sent as [`Ime::Commit`](bevy::window::Ime::Commit) and
+    /// one [`ReceivedCharacter`](bevy::window::ReceivedCharacter) per char.
+    ImeCommit {
+        value: String,
+        time: Instant,
+    },
+    /// A hardware key, sent to the primary window as [`KeyboardInput`](bevy::input::keyboard::KeyboardInput)
+    /// and, if it typed something, [`ReceivedCharacter`](bevy::window::ReceivedCharacter).
+    KeyEvent {
+        scan_code: u32,
+        key_code: Option<KeyCode>,
+        state: ButtonState,
+        character: Option<char>,
+        time: Instant,
+    },
+    /// Controller connections, buttons and axes, see [`crate::gamepad`].
+    GamepadEvents {
+        events: Vec<GamepadEvent>,
+        time: Instant,
+    },
+    /// The display cutout and system bar insets, in physical pixels.
+    SafeAreaInsets(SafeAreaInsets),
+    OnResume,
+    OnPause,
+    /// Replaces [`FramePacing::target`](crate::pacing::FramePacing::target).
+    SetTargetFrameRate(FrameRate),
+    /// Replaces the idle rate of the [`FramePacing`](crate::pacing::FramePacing).
+    SetIdleFrameRate {
+        rate: Option<FrameRate>,
+        idle_after: Duration,
+    },
+    /// Replaces the [`UpdateMode`].
+    SetUpdateMode(UpdateMode),
+    /// Replaces the [`RenderResolution`].
+    SetRenderResolution(RenderResolution),
+    /// Render a frame right away and complete the [`Completion`] once it is done.
+    RedrawNeeded(Completion),
+    /// Send [`BackPressed`](crate::back::BackPressed), run an update and complete with whether the
+    /// game consumed it.
+    BackPressed {
+        completion: Completion<bool>,
+        time: Instant,
+    },
+}
+
+/// Lets the host block until the game loop handled a command, and get its answer.
+#[derive(Debug)]
+pub struct Completion<T = ()>(Arc<(Mutex<Option<T>>, Condvar)>);
+
+impl<T> Completion<T> {
+    pub fn new() -> Self {
+        Self(Arc::new((Mutex::new(None), Condvar::new())))
+    }
+
+    /// Wakes up whoever waits on this completion with `value`.
+    pub fn complete_with(&self, value: T) {
+        let (done, done_var) = &*self.0;
+        *done.lock().unwrap() = Some(value);
+        done_var.notify_all();
+    }
+
+    /// Blocks until the completion was completed or `timeout` passed. Returns the answer, `None`
+    /// on timeout.
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<T> {
+        let (done, done_var) = &*self.0;
+        let done = done.lock().unwrap();
+        let (mut done, _) = done_var.wait_timeout_while(done, timeout, |done| done.is_none()).unwrap();
+        done.take()
+    }
+}
+
+impl Completion {
+    /// Wakes up whoever waits on this completion.
+    pub fn complete(&self) {
+        self.complete_with(());
+    }
+}
+
+/// Tells the host a destroyed surface can be released, e.g. the `SurfaceTexture` of a
+/// `TextureView`, once dropped. The game loop drops it after it let go of the surface, the command
+/// queue drops it with the command if no game loop takes it.
+pub struct SurfaceRelease(Option<Box<dyn FnOnce() + Send>>);
+
+impl SurfaceRelease {
+    pub fn new(on_released: impl FnOnce() + Send + 'static) -> Self {
+        Self(Some(Box::new(on_released)))
+    }
+}
+
+impl Drop for SurfaceRelease {
+    fn drop(&mut self) {
+        if let Some(on_released) = self.0.take() {
+            on_released();
+        }
+    }
+}
+
+impl std::fmt::Debug for SurfaceRelease {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SurfaceRelease").finish()
+    }
+}
+
+impl<T> Clone for Completion<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Default for Completion<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The channel between the host and the game loop.
+///
+/// The C API sends to a global queue created by [`init_command_queue`]. Insert a `CmdQueue` resource
+/// into the app to have the runner read from that queue instead.
+#[derive(Resource, Clone)]
+pub struct CmdQueue {
+    pub(crate) sender: Arc<Mutex<Sender<Cmd>>>,
+    pub(crate) receiver: Arc<Mutex<mpsc::Receiver<Cmd>>>,
+    pub(crate) surface_destroyed_handle_done: Arc<Mutex<bool>>,
+    pub(crate) surface_destroyed_handle_done_var: Arc<Condvar>,
+    pub(crate) running_loop: Arc<Mutex<bool>>,
+    pub(crate) hit_regions: Arc<RwLock<HitRegions>>,
+    #[cfg(target_os = "android")]
+    pub(crate) asset_manager: Arc<Mutex<Option<ndk::asset::AssetManager>>>,
+}
+
+pub(crate) static CMD_QUEUE: OnceLock<CmdQueue> = OnceLock::new();
+
+impl Cmd {
+    /// Queues the command on the global queue. The command is dropped with a warning if
+    /// [`init_command_queue`] has not been called yet.
+    pub fn send(self) -> Result<(), BridgeError> {
+        let cmd_queue = CMD_QUEUE.get();
+        if let Some(cmd_queue) = cmd_queue {
+            cmd_queue.send(self);
+            Ok(())
+        } else {
+            warn!("cmd_queue is None, can not send cmd: {:?}", self);
+            Err(BridgeError::NotInitialized)
+        }
+    }
+}
+
+impl CmdQueue {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel::<Cmd>();
+
+        CmdQueue {
+            sender: Arc::new(Mutex::new(tx)),
+            receiver: Arc::new(Mutex::new(rx)),
+            surface_destroyed_handle_done: Arc::new(Mutex::new(true)),
+            surface_destroyed_handle_done_var: Arc::new(Condvar::new()),
+            running_loop: Arc::new(Mutex::new(false)),
+            hit_regions: Arc::new(RwLock::new(HitRegions::Nothing)),
+            #[cfg(target_os = "android")]
+            asset_manager: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn send(&self, cmd: Cmd) {
+        let sender = self.sender.lock().unwrap();
+        sender.send(cmd).unwrap();
+    }
+
+    /// Sends `cmd` if a game loop is running. The check and the send happen under the
+    /// `running_loop` lock, so a loop that stops afterwards still finds `cmd` when it drains.
+    fn send_if_running(&self, cmd: Cmd) -> bool {
+        let running_loop = self.running_loop.lock().unwrap();
+
+        if *running_loop {
+            self.send(cmd);
+        }
+        *running_loop
+    }
+
+    /// Sends [`Cmd::SurfaceDestroyed`] and blocks until the game loop has released the surface.
+    /// Returns right away if no game loop is running.
+    pub fn destroy_surface(&self) {
+        let mut done = self.surface_destroyed_handle_done.lock().unwrap();
+        *done = false;
+
+        if !self.send_if_running(Cmd::SurfaceDestroyed(None)) {
+            *done = true;
+            return;
+        }
+
+        while !*done {
+            // wait for surfaceDestroyed to be handled
+            info!("wait for surfaceDestroyed to be handled");
+            done = self.surface_destroyed_handle_done_var.wait(done).unwrap();
+        }
+
+        info!("surfaceDestroyed handled");
+    }
+
+    /// Sends [`Cmd::SurfaceDestroyed`] without waiting, `release` is dropped once the game loop
+    /// released the surface. Dropped right away if no game loop is running.
+    pub fn destroy_surface_async(&self, release: SurfaceRelease) {
+        self.send_if_running(Cmd::SurfaceDestroyed(Some(release)));
+    }
+
+    /// Sends [`Cmd::RedrawNeeded`] and blocks until the game loop rendered a frame, at most for
+    /// `timeout`. Returns right away if no game loop is running.
+    pub fn redraw(&self, timeout: Duration) -> Result<(), BridgeError> {
+        let completion = Completion::new();
+        if !self.send_if_running(Cmd::RedrawNeeded(completion.clone())) {
+            return Ok(());
+        }
+
+        completion.wait_timeout(timeout).ok_or(BridgeError::Timeout)
+    }
+
+    /// Sends [`Cmd::BackPressed`] for a press at `time` and blocks until the game answered whether
+    /// it consumed the press, at most for `timeout`. Not consumed if no game loop is running.
+    pub fn back_pressed(&self, time: Instant, timeout: Duration) -> Result<bool, BridgeError> {
+        let completion = Completion::new();
+        if !self.send_if_running(Cmd::BackPressed { completion: completion.clone(), time }) {
+            return Ok(false);
+        }
+
+        completion.wait_timeout(timeout).ok_or(BridgeError::Timeout)
+    }
+
+    /// Whether a touch at `position`, in physical pixels, hits the game according to its
+    /// [`TouchHitTest`](crate::hit_test::TouchHitTest). Answered from the regions computed by the
+    /// last update, without waiting for the game loop.
+    pub fn hit_test(&self, position: Vec2) -> bool {
+        self.hit_regions.read().unwrap().contains(position)
+    }
+
+    /// Discards every queued command that has not been handled yet. Whoever waits on a discarded
+    /// command is woken up as if a game loop had handled it.
+    pub fn drain(&self) {
+        let rx = self.receiver.lock().unwrap();
+        for cmd in rx.try_iter() {
+            info!("discard cmd: {:?}", cmd);
+            match cmd {
+                Cmd::SurfaceDestroyed(None) => self.notify_surface_destroyed_handled(),
+                Cmd::RedrawNeeded(completion) => completion.complete(),
+                Cmd::BackPressed { completion, .. } => completion.complete_with(false),
+                // a SurfaceRelease tells the host when dropped here
+                _ => {}
+            }
+        }
+    }
+
+    /// Marks the game loop as stopped and drains what was sent while it was stopping.
+    pub(crate) fn stop_running(&self) {
+        *self.running_loop.lock().unwrap() = false;
+        self.drain();
+    }
+
+    /// Wakes up the host waiting in [`destroy_surface`](Self::destroy_surface).
+    pub(crate) fn notify_surface_destroyed_handled(&self) {
+        info!("surfaceDestroyed handled, modify done to true");
+        let mut done = self.surface_destroyed_handle_done.lock().unwrap();
+        *done = true;
+        info!("surfaceDestroyed handled, notify_one");
+        self.surface_destroyed_handle_done_var.notify_one();
+    }
+}
+
+impl Default for CmdQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+pub fn init_command_queue() {
+    CMD_QUEUE.get_or_init(CmdQueue::new);
+}
+
+/// The global queue, once [`init_command_queue`] was called.
+pub(crate) fn global_queue() -> Result<&'static CmdQueue, BridgeError> {
+    CMD_QUEUE.get().ok_or(BridgeError::NotInitialized)
+}
+
+/// Discards every command queued on the global queue that has not been handled yet.
+pub fn drain_command_queue() {
+    if let Some(cmd_queue) = CMD_QUEUE.get() {
+        cmd_queue.drain();
+    }
+}
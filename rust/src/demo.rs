@@ -0,0 +1,189 @@
+use bevy::input::touch::TouchPhase;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use bevy::window::WindowMode;
+use bevy::winit::WinitPlugin;
+
+use crate::{BevySurfacePlugin, SafeAreaPadding};
+
+/// Builds the demo app: a rotating quad that follows touches and a button click counter.
+pub fn build_demo_app(app: &mut App) {
+    app
+        .add_plugins(BevySurfacePlugin)
+        .add_plugins(
+            DefaultPlugins
+                .set(
+                    WindowPlugin {
+                        primary_window: Some(Window {
+                            resizable: false,
+                            mode: WindowMode::BorderlessFullscreen,
+                            ..default()
+                        }),
+                        ..default()
+                    }
+                )
+                .set(LogPlugin {
+                    level: bevy::log::Level::DEBUG,
+                    ..default()
+                })
+                .set(ImagePlugin::default_nearest())
+                .disable::<WinitPlugin>() // removed by bevy feature selection
+        )
+        .insert_resource(LastTouchMove::default())
+        .add_systems(Startup, setup)
+        .add_systems(Update,
+                     (
+                         update,
+                         move_system,
+                         btn_system,
+                     ),
+        )
+    ;
+}
+
+#[derive(Component)]
+struct Elm;
+
+#[derive(Component)]
+struct TextCount;
+
+#[derive(Resource, Deref, DerefMut, Default)]
+struct LastTouchMove(Vec2);
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_loader: Res<AssetServer>,
+) {
+    commands.spawn(Camera2dBundle::default());
+
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Quad::new(Vec2::new(50., 100.)).into()).into(),
+            material: materials.add(ColorMaterial::from(Color::PURPLE)),
+            ..default()
+        },
+        Elm
+    ));
+
+    // the counter sits in the bottom right corner of the safe area
+    commands.spawn((NodeBundle {
+        style: Style {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::FlexEnd,
+            align_items: AlignItems::FlexEnd,
+            ..default()
+        },
+        ..default()
+    }, SafeAreaPadding))
+        .with_children(|parent| {
+            parent.spawn(NodeBundle {
+                style: Style {
+                    height: Val::Px(100.),
+                    margin: UiRect::all(Val::Px(10.)),
+                    align_items: AlignItems::Center,
+                    justify_items: JustifyItems::Center,
+                    flex_direction: FlexDirection::Row,
+                    ..default()
+                },
+                ..default()
+            })
+                .with_children(|parent| {
+                    parent.spawn((TextBundle::from_sections(vec![
+                        TextSection {
+                            value: "Button Click Count: ".to_string(),
+                            style: TextStyle {
+                                font: asset_loader.load("fonts/FiraMono-Medium.ttf"),
+                                font_size: 30.,
+                                color: Color::WHITE,
+                            },
+                        },
+                        TextSection {
+                            value: "0".to_string(),
+                            style: TextStyle {
+                                font: asset_loader.load("fonts/FiraMono-Medium.ttf"),
+                                font_size: 30.,
+                                color: Color::RED,
+                            },
+                        },
+                    ]),
+                                  TextCount
+                    ));
+
+                    parent.spawn(ButtonBundle {
+                        style: Style {
+                            height: Val::Px(50.),
+                            width: Val::Px(200.),
+                            align_items: AlignItems::Center,
+                            justify_items: JustifyItems::Center,
+                            margin: UiRect::all(Val::Px(10.)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Click Meeee",
+                                TextStyle {
+                                    font: asset_loader.load("fonts/FiraMono-Medium.ttf"),
+                                    font_size: 30.,
+                                    color: Color::BLACK,
+                                },
+                            ));
+                        });
+                });
+        });
+}
+
+fn update(
+    time: Res<Time>,
+    mut query: Query<&mut Transform, With<Elm>>,
+) {
+    for mut transform in query.iter_mut() {
+        transform.rotate_z(time.delta_seconds() * 2.3);
+    }
+}
+
+
+fn move_system(
+    mut query: Query<&mut Transform, With<Elm>>,
+    mut touch_input: EventReader<TouchInput>,
+    mut last_touch_move: ResMut<LastTouchMove>,
+) {
+    for touch in touch_input.read() {
+        match touch.phase {
+            TouchPhase::Started => {
+                last_touch_move.0 = touch.position;
+            }
+            TouchPhase::Moved => {
+                for mut transform in query.iter_mut() {
+                    transform.translation.x += touch.position.x - last_touch_move.x;
+                    // due to the coordinate system of android, y axis is reversed.
+                    transform.translation.y -= touch.position.y - last_touch_move.y;
+
+                    last_touch_move.0 = touch.position;
+                }
+            }
+            TouchPhase::Ended => {}
+            TouchPhase::Canceled => {}
+        }
+    }
+}
+
+fn btn_system(
+    mut query: Query<&mut Text, With<TextCount>>,
+    mut btn_query: Query<&mut Interaction, (Changed<Interaction>, With<Button>)>,
+    mut btn_click_count: Local<u32>,
+) {
+    for interaction in btn_query.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            *btn_click_count += 1;
+            for mut text in query.iter_mut() {
+                text.sections[1].value = btn_click_count.to_string();
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::ffi::{c_char, CString};
+use std::fmt;
+use std::sync::Mutex;
+
+use bevy::log::error;
+
+use crate::lifecycle::{SurfaceState, SurfaceTransition};
+
+/// Called with the [`BridgeError::code`] and a nul terminated message whenever the bridge reports an
+/// error. It may be called from any thread, usually the game thread.
+pub type ErrorCallback = extern "C" fn(code: i32, message: *const c_char);
+
+static ERROR_CALLBACK: Mutex<Option<ErrorCallback>> = Mutex::new(None);
+
+/// Failures of the host <-> game protocol. They are reported to the host instead of panicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BridgeError {
+    /// `initialize` was not called before the command.
+    NotInitialized,
+    /// A null pointer was passed to the C API.
+    NullPointer,
+    /// `touchEvents` or `mouseEvent` got a `MotionEvent` action it does not know.
+    UnknownMotionAction(i32),
+    /// A lifecycle command arrived in a state that does not accept it.
+    InvalidTransition {
+        transition: SurfaceTransition,
+        state: SurfaceState,
+    },
+    /// The app has no primary window to attach the surface to.
+    NoPrimaryWindow,
+    /// The game loop did not handle a synchronous command in time.
+    Timeout,
+    /// A batch passed to `touchEvents` does not match its pointer and history counts.
+    MalformedTouchData,
+    /// `keyEvent` got a `KeyEvent` action it does not know.
+    UnknownKeyAction(i32),
+}
+
+impl BridgeError {
+    /// The code returned by the C API and passed to the [`ErrorCallback`]. `0` means success.
+    pub fn code(&self) -> i32 {
+        match self {
+            BridgeError::NotInitialized => 1,
+            BridgeError::NullPointer => 2,
+            BridgeError::UnknownMotionAction(_) => 3,
+            BridgeError::InvalidTransition { .. } => 4,
+            BridgeError::NoPrimaryWindow => 5,
+            BridgeError::Timeout => 6,
+            BridgeError::MalformedTouchData => 7,
+            BridgeError::UnknownKeyAction(_) => 8,
+        }
+    }
+}
+
+impl fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BridgeError::NotInitialized => write!(f, "initialize has not been called"),
+            BridgeError::NullPointer => write!(f, "null pointer passed to the C API"),
+            BridgeError::UnknownMotionAction(action) => write!(f, "unknown MotionEvent action {action}"),
+            BridgeError::InvalidTransition { transition, state } => {
+                write!(f, "{transition:?} is not allowed in surface state {state:?}")
+            }
+            BridgeError::NoPrimaryWindow => write!(f, "the app has no primary window"),
+            BridgeError::Timeout => write!(f, "the game loop did not respond in time"),
+            BridgeError::MalformedTouchData => write!(f, "malformed touch batch"),
+            BridgeError::UnknownKeyAction(action) => write!(f, "unknown KeyEvent action {action}"),
+        }
+    }
+}
+
+impl std::error::Error for BridgeError {}
+
+/// Maps a result to the code returned by the C API.
+pub fn result_code(result: Result<(), BridgeError>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(err) => err.code(),
+    }
+}
+
+/// Sets the callback errors from the game thread are reported to, `None` removes it.
+pub fn set_error_callback(callback: Option<ErrorCallback>) {
+    let mut error_callback = ERROR_CALLBACK.lock().unwrap();
+    *error_callback = callback;
+}
+
+/// Logs `err` and passes it to the [`ErrorCallback`], if one is set.
+pub fn report_error(err: BridgeError) {
+    error!("{}", err);
+
+    let error_callback = *ERROR_CALLBACK.lock().unwrap();
+    if let Some(error_callback) = error_callback {
+        let message = CString::new(err.to_string()).unwrap_or_default();
+        error_callback(err.code(), message.as_ptr());
+    }
+}
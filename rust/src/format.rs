@@ -0,0 +1,261 @@
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use bevy::window::{CompositeAlphaMode, PrimaryWindow};
+
+/// The android `PixelFormat` of the surface buffers, as reported by `surfaceChanged`.
+///
+/// It only decides how the window is composited. bevy picks the swap chain format from what the
+/// surface supports, so e.g. a 565 or 1010102 surface is still rendered in whatever format wgpu
+/// prefers for it.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceFormat {
+    /// `PixelFormat.UNKNOWN`, no surface reported its format yet.
+    #[default]
+    Unknown,
+    /// `PixelFormat.OPAQUE`, the system picks an opaque format.
+    Opaque,
+    /// `PixelFormat.TRANSLUCENT`, the system picks a format with several alpha bits.
+    Translucent,
+    Rgba8888,
+    Rgbx8888,
+    Rgb888,
+    /// The default of a `SurfaceView`.
+    Rgb565,
+    /// `PixelFormat.RGBA_F16`, for HDR.
+    RgbaF16,
+    /// `PixelFormat.RGBA_1010102`, for wide gamut.
+    Rgba1010102,
+    Other(i32),
+}
+
+impl SurfaceFormat {
+    pub fn from_android(format: i32) -> Self {
+        match format {
+            0 => SurfaceFormat::Unknown,
+            -1 => SurfaceFormat::Opaque,
+            // TRANSPARENT (-2) is only one alpha bit, treat it the same
+            -2 | -3 => SurfaceFormat::Translucent,
+            1 => SurfaceFormat::Rgba8888,
+            2 => SurfaceFormat::Rgbx8888,
+            3 => SurfaceFormat::Rgb888,
+            4 => SurfaceFormat::Rgb565,
+            0x16 => SurfaceFormat::RgbaF16,
+            0x2b => SurfaceFormat::Rgba1010102,
+            format => SurfaceFormat::Other(format),
+        }
+    }
+
+    pub fn to_android(self) -> i32 {
+        match self {
+            SurfaceFormat::Unknown => 0,
+            SurfaceFormat::Opaque => -1,
+            SurfaceFormat::Translucent => -3,
+            SurfaceFormat::Rgba8888 => 1,
+            SurfaceFormat::Rgbx8888 => 2,
+            SurfaceFormat::Rgb888 => 3,
+            SurfaceFormat::Rgb565 => 4,
+            SurfaceFormat::RgbaF16 => 0x16,
+            SurfaceFormat::Rgba1010102 => 0x2b,
+            SurfaceFormat::Other(format) => format,
+        }
+    }
+
+    pub fn has_alpha(self) -> bool {
+        matches!(
+            self,
+            SurfaceFormat::Translucent | SurfaceFormat::Rgba8888 | SurfaceFormat::RgbaF16 | SurfaceFormat::Rgba1010102
+        )
+    }
+
+    /// How the window composites: formats with alpha leave it to the system, android's vulkan
+    /// swapchains only support `Inherit` and `Opaque`. bevy's default until the format is known.
+    pub fn alpha_mode(self) -> CompositeAlphaMode {
+        match self {
+            SurfaceFormat::Unknown => CompositeAlphaMode::Auto,
+            format if format.has_alpha() => CompositeAlphaMode::Inherit,
+            _ => CompositeAlphaMode::Opaque,
+        }
+    }
+}
+
+/// Set it to ask the host for surface buffers in another format, e.g. [`SurfaceFormat::Rgba1010102`]
+/// or [`SurfaceFormat::RgbaF16`] on wide gamut or HDR displays. The host calls
+/// `SurfaceHolder.setFormat` and reports the result with the next `surfaceChanged`.
+///
+/// bevy still picks the swap chain format of the window itself, check [`SurfaceFormat`] for what
+/// the buffers actually are.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RequestedSurfaceFormat(pub Option<SurfaceFormat>);
+
+/// How the surface is composited with the android views under it.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceComposition {
+    /// The alpha mode follows the [`SurfaceFormat`].
+    #[default]
+    Auto,
+    /// Blend over the views under the surface with this alpha mode. A still default [`ClearColor`]
+    /// becomes transparent and [`SurfaceFormat::Translucent`] is requested unless another format
+    /// is, both are undone when going back to `Auto`. The host still has to put the `SurfaceView`
+    /// on top, with `setZOrderOnTop`.
+    ///
+    /// `PreMultiplied` and `PostMultiplied` depend on the driver, android's own vulkan swapchain
+    /// only offers `Inherit`, which SurfaceFlinger composites as premultiplied.
+    Transparent(CompositeAlphaMode),
+}
+
+impl SurfaceComposition {
+    pub fn alpha_mode(self, format: SurfaceFormat) -> CompositeAlphaMode {
+        match self {
+            SurfaceComposition::Auto => format.alpha_mode(),
+            SurfaceComposition::Transparent(alpha_mode) => alpha_mode,
+        }
+    }
+}
+
+/// What [`SurfaceComposition::Transparent`] replaced, put back by [`SurfaceComposition::Auto`].
+#[derive(Default)]
+pub(crate) struct ReplacedByTransparent {
+    clear_color: Option<Color>,
+    format: Option<SurfaceFormat>,
+}
+
+pub(crate) fn apply_surface_composition(
+    composition: Res<SurfaceComposition>,
+    format: Res<SurfaceFormat>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    clear_color: Option<ResMut<ClearColor>>,
+    mut requested: ResMut<RequestedSurfaceFormat>,
+    mut replaced: Local<ReplacedByTransparent>,
+) {
+    if !composition.is_changed() && !format.is_changed() {
+        return;
+    }
+
+    let alpha_mode = composition.alpha_mode(*format);
+    for mut window in &mut windows {
+        if window.composite_alpha_mode != alpha_mode {
+            window.composite_alpha_mode = alpha_mode;
+        }
+    }
+
+    if !composition.is_changed() {
+        return;
+    }
+    if *composition == SurfaceComposition::Auto {
+        let replaced = std::mem::take(&mut *replaced);
+        if let (Some(color), Some(mut clear_color)) = (replaced.clear_color, clear_color) {
+            if clear_color.0 == Color::NONE {
+                clear_color.0 = color;
+            }
+        }
+        if replaced.format.is_some() && requested.0 == Some(SurfaceFormat::Translucent) {
+            requested.0 = replaced.format;
+        }
+        return;
+    }
+
+    if requested.0.is_none() {
+        requested.0 = Some(SurfaceFormat::Translucent);
+        // back to an opaque surface if it never reported its format
+        replaced.format = Some(match *format {
+            SurfaceFormat::Unknown => SurfaceFormat::Opaque,
+            format => format,
+        });
+    }
+    if let Some(mut clear_color) = clear_color {
+        if clear_color.0 == ClearColor::default().0 {
+            replaced.clear_color = Some(clear_color.0);
+            clear_color.0 = Color::NONE;
+        }
+    }
+}
+
+/// Called with the android `PixelFormat` the game wants, on the game thread.
+pub type SurfaceFormatCallback = extern "C" fn(format: i32);
+
+static SURFACE_FORMAT_CALLBACK: Mutex<Option<SurfaceFormatCallback>> = Mutex::new(None);
+
+/// Sets the callback that forwards [`RequestedSurfaceFormat`] to the host, `None` removes it.
+pub fn set_surface_format_callback(callback: Option<SurfaceFormatCallback>) {
+    let mut surface_format_callback = SURFACE_FORMAT_CALLBACK.lock().unwrap();
+    *surface_format_callback = callback;
+}
+
+pub(crate) fn forward_surface_format_requests(requested: Res<RequestedSurfaceFormat>) {
+    if !requested.is_changed() {
+        return;
+    }
+    let Some(format) = requested.0 else {
+        return;
+    };
+
+    let surface_format_callback = *SURFACE_FORMAT_CALLBACK.lock().unwrap();
+    if let Some(surface_format_callback) = surface_format_callback {
+        surface_format_callback(format.to_android());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+    use bevy::window::{CompositeAlphaMode, PrimaryWindow};
+
+    use super::{apply_surface_composition, RequestedSurfaceFormat, SurfaceComposition, SurfaceFormat};
+
+    #[test]
+    fn android_formats_map_to_alpha_modes() {
+        for format in [0, -1, -3, 1, 2, 3, 4, 0x16, 0x2b, 42] {
+            assert_eq!(SurfaceFormat::from_android(format).to_android(), format);
+        }
+
+        assert_eq!(SurfaceFormat::from_android(0x2b).alpha_mode(), CompositeAlphaMode::Inherit);
+        assert_eq!(SurfaceFormat::from_android(2).alpha_mode(), CompositeAlphaMode::Opaque);
+        // bevy's own default until a surface reports its format
+        assert_eq!(SurfaceFormat::default().alpha_mode(), CompositeAlphaMode::Auto);
+    }
+
+    #[test]
+    fn transparent_composition_clears_to_transparent() {
+        let mut app = App::new();
+        app
+            .init_resource::<SurfaceFormat>()
+            .init_resource::<RequestedSurfaceFormat>()
+            .init_resource::<ClearColor>()
+            .insert_resource(SurfaceComposition::Transparent(CompositeAlphaMode::PreMultiplied))
+            .add_systems(Update, apply_surface_composition);
+        let window = app.world.spawn((Window::default(), PrimaryWindow)).id();
+        app.update();
+
+        assert_eq!(app.world.get::<Window>(window).unwrap().composite_alpha_mode, CompositeAlphaMode::PreMultiplied);
+        assert_eq!(app.world.resource::<ClearColor>().0, Color::NONE);
+        assert_eq!(app.world.resource::<RequestedSurfaceFormat>().0, Some(SurfaceFormat::Translucent));
+
+        // the host switched the surface to the requested format
+        app.insert_resource(SurfaceFormat::Translucent);
+        app.update();
+        app.insert_resource(SurfaceComposition::Auto);
+        app.update();
+        assert_eq!(app.world.resource::<ClearColor>().0, ClearColor::default().0);
+        assert_eq!(app.world.resource::<RequestedSurfaceFormat>().0, Some(SurfaceFormat::Opaque));
+
+        app.insert_resource(SurfaceFormat::Rgbx8888);
+        app.update();
+        assert_eq!(app.world.get::<Window>(window).unwrap().composite_alpha_mode, CompositeAlphaMode::Opaque);
+    }
+
+    #[test]
+    fn unknown_format_keeps_the_default_alpha_mode() {
+        let mut app = App::new();
+        app
+            .init_resource::<SurfaceFormat>()
+            .init_resource::<RequestedSurfaceFormat>()
+            .init_resource::<SurfaceComposition>()
+            .add_systems(Update, apply_surface_composition);
+        let window = app.world.spawn((Window::default(), PrimaryWindow)).id();
+        app.update();
+
+        assert_eq!(app.world.get::<Window>(window).unwrap().composite_alpha_mode, CompositeAlphaMode::Auto);
+        assert_eq!(app.world.resource::<RequestedSurfaceFormat>().0, None);
+    }
+}
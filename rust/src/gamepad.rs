@@ -0,0 +1,127 @@
+use bevy::input::gamepad::{GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadConnection, GamepadConnectionEvent, GamepadEvent, GamepadInfo};
+use bevy::prelude::*;
+
+// `MotionEvent.AXIS_*` values reported by game controllers.
+const AXIS_X: i32 = 0;
+const AXIS_Y: i32 = 1;
+const AXIS_Z: i32 = 11;
+const AXIS_RZ: i32 = 14;
+const AXIS_HAT_X: i32 = 15;
+const AXIS_HAT_Y: i32 = 16;
+const AXIS_LTRIGGER: i32 = 17;
+const AXIS_RTRIGGER: i32 = 18;
+const AXIS_GAS: i32 = 22;
+const AXIS_BRAKE: i32 = 23;
+
+/// The bevy gamepad of an android `InputDevice` id.
+pub fn gamepad(device_id: i32) -> Gamepad {
+    Gamepad::new(device_id as usize)
+}
+
+pub fn connection_event(device_id: i32, name: String) -> GamepadEvent {
+    GamepadConnectionEvent::new(gamepad(device_id), GamepadConnection::Connected(GamepadInfo { name })).into()
+}
+
+pub fn disconnection_event(device_id: i32) -> GamepadEvent {
+    GamepadConnectionEvent::new(gamepad(device_id), GamepadConnection::Disconnected).into()
+}
+
+/// Maps a gamepad `KEYCODE_*` to the bevy button, `None` if it is not a gamepad button.
+pub fn button_type(keycode: i32) -> Option<GamepadButtonType> {
+    use GamepadButtonType::*;
+
+    let button_type = match keycode {
+        19 => DPadUp,
+        20 => DPadDown,
+        21 => DPadLeft,
+        22 => DPadRight,
+        96 => South,
+        97 => East,
+        98 => C,
+        99 => West,
+        100 => North,
+        101 => Z,
+        102 => LeftTrigger,
+        103 => RightTrigger,
+        104 => LeftTrigger2,
+        105 => RightTrigger2,
+        106 => LeftThumb,
+        107 => RightThumb,
+        108 => Start,
+        109 => Select,
+        110 => Mode,
+        // KEYCODE_BUTTON_1 ..= KEYCODE_BUTTON_16
+        188..=203 => Other((keycode - 187) as u8),
+        _ => return None,
+    };
+
+    Some(button_type)
+}
+
+pub fn button_event(device_id: i32, button_type: GamepadButtonType, pressed: bool) -> GamepadEvent {
+    let value = if pressed { 1. } else { 0. };
+    GamepadButtonChangedEvent::new(gamepad(device_id), button_type, value).into()
+}
+
+/// The events for a change of a `MotionEvent` axis.
+///
+/// Sticks become bevy axes with Y pointing up, analog triggers become the values of the
+/// `*Trigger2` buttons and the hat becomes the d-pad buttons.
+pub fn axis_events(device_id: i32, axis: i32, value: f32) -> Vec<GamepadEvent> {
+    let gamepad = gamepad(device_id);
+    let axis_event = |axis_type, value| GamepadAxisChangedEvent::new(gamepad, axis_type, value).into();
+    let button_event = |button_type, value| GamepadButtonChangedEvent::new(gamepad, button_type, value).into();
+
+    match axis {
+        AXIS_X => vec![axis_event(GamepadAxisType::LeftStickX, value)],
+        AXIS_Y => vec![axis_event(GamepadAxisType::LeftStickY, -value)],
+        AXIS_Z => vec![axis_event(GamepadAxisType::RightStickX, value)],
+        AXIS_RZ => vec![axis_event(GamepadAxisType::RightStickY, -value)],
+        AXIS_LTRIGGER | AXIS_BRAKE => vec![button_event(GamepadButtonType::LeftTrigger2, value)],
+        AXIS_RTRIGGER | AXIS_GAS => vec![button_event(GamepadButtonType::RightTrigger2, value)],
+        AXIS_HAT_X => vec![
+            button_event(GamepadButtonType::DPadLeft, (-value).max(0.)),
+            button_event(GamepadButtonType::DPadRight, value.max(0.)),
+        ],
+        AXIS_HAT_Y => vec![
+            button_event(GamepadButtonType::DPadUp, (-value).max(0.)),
+            button_event(GamepadButtonType::DPadDown, value.max(0.)),
+        ],
+        _ => match u8::try_from(axis) {
+            Ok(axis) => vec![axis_event(GamepadAxisType::Other(axis), value)],
+            Err(_) => Vec::new(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::gamepad::{GamepadAxisChangedEvent, GamepadButtonChangedEvent, GamepadEvent};
+    use bevy::prelude::*;
+
+    use super::{axis_events, button_type};
+
+    #[test]
+    fn android_buttons_map_to_bevy() {
+        assert_eq!(button_type(96), Some(GamepadButtonType::South));
+        assert_eq!(button_type(100), Some(GamepadButtonType::North));
+        assert_eq!(button_type(108), Some(GamepadButtonType::Start));
+        assert_eq!(button_type(188), Some(GamepadButtonType::Other(1)));
+        assert_eq!(button_type(29), None);
+    }
+
+    #[test]
+    fn android_axes_map_to_bevy() {
+        let gamepad = Gamepad::new(7);
+        assert_eq!(axis_events(7, 1, 0.5), vec![
+            GamepadEvent::Axis(GamepadAxisChangedEvent::new(gamepad, GamepadAxisType::LeftStickY, -0.5)),
+        ]);
+        assert_eq!(axis_events(7, 17, 0.25), vec![
+            GamepadEvent::Button(GamepadButtonChangedEvent::new(gamepad, GamepadButtonType::LeftTrigger2, 0.25)),
+        ]);
+        assert_eq!(axis_events(7, 15, -1.), vec![
+            GamepadEvent::Button(GamepadButtonChangedEvent::new(gamepad, GamepadButtonType::DPadLeft, 1.)),
+            GamepadEvent::Button(GamepadButtonChangedEvent::new(gamepad, GamepadButtonType::DPadRight, 0.)),
+        ]);
+    }
+}
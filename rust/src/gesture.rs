@@ -0,0 +1,95 @@
+use bevy::input::touch::TouchPhase;
+use bevy::prelude::*;
+
+/// Turns two-finger pinches and twists into
+/// [`TouchpadMagnify`](bevy::input::touchpad::TouchpadMagnify) and
+/// [`TouchpadRotate`](bevy::input::touchpad::TouchpadRotate) events, so code written for desktop
+/// trackpads works on touch screens. The touches are still forwarded.
+pub struct TouchGesturePlugin;
+
+impl Plugin for TouchGesturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchGestures>();
+    }
+}
+
+/// The recognizer state. The runner feeds it every touch while the resource exists.
+#[derive(Resource, Debug, Default)]
+pub struct TouchGestures {
+    /// Touches that are down, in the order they started. The first two form the gesture.
+    touches: Vec<(u64, Vec2)>,
+}
+
+impl TouchGestures {
+    /// Tracks `input` and returns the magnification and the counterclockwise rotation in degrees
+    /// it caused, if it moved one of the two gesture fingers.
+    pub fn touch(&mut self, input: &TouchInput) -> Option<(f32, f32)> {
+        let index = self.touches.iter().position(|(id, _)| *id == input.id);
+
+        match (input.phase, index) {
+            (TouchPhase::Started, None) => {
+                self.touches.push((input.id, input.position));
+                None
+            }
+            (TouchPhase::Moved, Some(index)) => {
+                let before = self.span();
+                self.touches[index].1 = input.position;
+                let after = self.span();
+
+                let (before, after) = (before?, after?);
+                if index > 1 || before.length() == 0. {
+                    return None;
+                }
+
+                let magnify = after.length() / before.length() - 1.;
+                // screen coordinates point down, so clockwise on screen is a positive angle
+                let rotate = -before.angle_between(after).to_degrees();
+                (magnify != 0. || rotate != 0.).then_some((magnify, rotate))
+            }
+            (TouchPhase::Ended | TouchPhase::Canceled, Some(index)) => {
+                self.touches.remove(index);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// The vector from the first to the second finger.
+    fn span(&self) -> Option<Vec2> {
+        match self.touches.as_slice() {
+            [(_, first), (_, second), ..] => Some(*second - *first),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::touch::TouchPhase;
+    use bevy::prelude::*;
+
+    use super::TouchGestures;
+
+    fn touch(phase: TouchPhase, id: u64, x: f32, y: f32) -> TouchInput {
+        TouchInput { phase, position: Vec2::new(x, y), force: None, id }
+    }
+
+    #[test]
+    fn pinch_and_twist() {
+        let mut gestures = TouchGestures::default();
+        assert_eq!(gestures.touch(&touch(TouchPhase::Started, 1, 0., 0.)), None);
+        assert_eq!(gestures.touch(&touch(TouchPhase::Moved, 1, 0., 0.)), None);
+        assert_eq!(gestures.touch(&touch(TouchPhase::Started, 2, 100., 0.)), None);
+
+        let (magnify, rotate) = gestures.touch(&touch(TouchPhase::Moved, 2, 200., 0.)).unwrap();
+        assert_eq!((magnify, rotate), (1., 0.));
+
+        // the second finger moves up on screen, around the first: counterclockwise
+        let (magnify, rotate) = gestures.touch(&touch(TouchPhase::Moved, 2, 0., -200.)).unwrap();
+        assert!(magnify.abs() < 1e-6);
+        assert!((rotate - 90.).abs() < 1e-3);
+
+        assert_eq!(gestures.touch(&touch(TouchPhase::Ended, 1, 0., 0.)), None);
+        assert_eq!(gestures.touch(&touch(TouchPhase::Moved, 2, 0., -100.)), None);
+    }
+}
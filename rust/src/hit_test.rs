@@ -0,0 +1,106 @@
+use std::sync::{Arc, RwLock};
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::resolution::BufferScale;
+
+/// Which touches the host should send to the game, so the others can fall through to the android
+/// views around or under the surface. The host asks with `hitTest` when a gesture starts.
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub enum TouchHitTest {
+    /// The game takes every touch.
+    #[default]
+    Everything,
+    /// Only touches on visible UI nodes with an [`Interaction`].
+    InteractiveNodes,
+    /// Only touches inside these rectangles, in logical pixels.
+    Regions(Vec<Rect>),
+}
+
+/// What the game hits, as last computed on the game thread. Shared with the host so `hitTest`
+/// answers without waiting for the game loop.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum HitRegions {
+    /// No game is running, nothing is hit.
+    #[default]
+    Nothing,
+    Everything,
+    /// Rectangles in surface pixels.
+    Rects(Vec<Rect>),
+}
+
+impl HitRegions {
+    /// Whether `position`, in surface pixels, hits the game.
+    pub fn contains(&self, position: Vec2) -> bool {
+        match self {
+            HitRegions::Nothing => false,
+            HitRegions::Everything => true,
+            HitRegions::Rects(rects) => rects.iter().any(|rect| rect.contains(position)),
+        }
+    }
+}
+
+/// The [`HitRegions`] of the queue the runner reads from.
+#[derive(Resource, Debug, Clone, Deref)]
+pub(crate) struct SharedHitRegions(pub(crate) Arc<RwLock<HitRegions>>);
+
+pub(crate) fn update_hit_regions(
+    shared: Option<Res<SharedHitRegions>>,
+    hit_test: Res<TouchHitTest>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    nodes: Query<(&Node, &GlobalTransform, &InheritedVisibility), With<Interaction>>,
+    ui_scale: Option<Res<UiScale>>,
+    buffer_scale: Res<BufferScale>,
+) {
+    let Some(shared) = shared else {
+        return;
+    };
+    let scale_factor = windows.get_single().map_or(1., |window| window.scale_factor());
+
+    let regions = match &*hit_test {
+        TouchHitTest::Everything => HitRegions::Everything,
+        TouchHitTest::InteractiveNodes => {
+            let ui_scale = ui_scale.map_or(1., |ui_scale| ui_scale.0);
+            HitRegions::Rects(nodes
+                .iter()
+                .filter(|(_, _, visibility)| visibility.get())
+                .map(|(node, transform, _)| node.physical_rect(transform, scale_factor, ui_scale))
+                .collect())
+        }
+        TouchHitTest::Regions(rects) => {
+            let scale_factor = scale_factor as f32;
+            HitRegions::Rects(rects
+                .iter()
+                .map(|rect| Rect::from_corners(rect.min * scale_factor, rect.max * scale_factor))
+                .collect())
+        }
+    };
+
+    // the host asks with surface pixels
+    let regions = match regions {
+        HitRegions::Rects(rects) if **buffer_scale != Vec2::ONE => HitRegions::Rects(rects
+            .into_iter()
+            .map(|rect| Rect::from_corners(rect.min / **buffer_scale, rect.max / **buffer_scale))
+            .collect()),
+        regions => regions,
+    };
+
+    *shared.write().unwrap() = regions;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::HitRegions;
+
+    #[test]
+    fn hit_regions_contain() {
+        let rects = HitRegions::Rects(vec![Rect::new(0., 0., 10., 10.)]);
+        assert!(rects.contains(Vec2::new(5., 5.)));
+        assert!(!rects.contains(Vec2::new(15., 5.)));
+        assert!(HitRegions::Everything.contains(Vec2::new(15., 5.)));
+        assert!(!HitRegions::Nothing.contains(Vec2::new(5., 5.)));
+    }
+}
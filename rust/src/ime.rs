@@ -0,0 +1,75 @@
+use std::sync::Mutex;
+
+use bevy::prelude::*;
+use bevy::window::{Ime, PrimaryWindow};
+
+use crate::resolution::BufferScale;
+
+/// Called when the primary window turns its IME on or off, or moves it. `x`/`y` is
+/// `Window.ime_position` in surface pixels. It is called on the game thread.
+pub type ImeCallback = extern "C" fn(enabled: bool, x: f32, y: f32);
+
+static IME_CALLBACK: Mutex<Option<ImeCallback>> = Mutex::new(None);
+
+/// Sets the callback that shows and hides the soft keyboard, `None` removes it.
+pub fn set_ime_callback(callback: Option<ImeCallback>) {
+    let mut ime_callback = IME_CALLBACK.lock().unwrap();
+    *ime_callback = callback;
+}
+
+/// Forwards `ime_enabled` / `ime_position` changes of the primary window to the [`ImeCallback`],
+/// and sends [`Ime::Enabled`] / [`Ime::Disabled`] like winit does.
+#[allow(clippy::type_complexity)]
+pub(crate) fn forward_ime_requests(
+    windows: Query<(Entity, &Window), (With<PrimaryWindow>, Changed<Window>)>,
+    mut forwarded: Local<(bool, Vec2)>,
+    mut ime: EventWriter<Ime>,
+    buffer_scale: Res<BufferScale>,
+) {
+    let Ok((entity, window)) = windows.get_single() else {
+        return;
+    };
+
+    let position = window.ime_position * window.scale_factor() as f32 / **buffer_scale;
+    if *forwarded == (window.ime_enabled, position) {
+        return;
+    }
+
+    if forwarded.0 != window.ime_enabled {
+        ime.send(match window.ime_enabled {
+            true => Ime::Enabled { window: entity },
+            false => Ime::Disabled { window: entity },
+        });
+    }
+    *forwarded = (window.ime_enabled, position);
+
+    let ime_callback = *IME_CALLBACK.lock().unwrap();
+    if let Some(ime_callback) = ime_callback {
+        ime_callback(window.ime_enabled, position.x, position.y);
+    }
+}
+
+/// The byte index in `text` of the UTF-16 code unit `index`, as android counts composing cursors.
+pub fn utf16_to_byte_index(text: &str, index: usize) -> usize {
+    let mut units = 0;
+    for (byte_index, c) in text.char_indices() {
+        if units >= index {
+            return byte_index;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::utf16_to_byte_index;
+
+    #[test]
+    fn utf16_cursor_to_byte_index() {
+        assert_eq!(utf16_to_byte_index("abc", 2), 2);
+        assert_eq!(utf16_to_byte_index("日本語", 1), 3);
+        assert_eq!(utf16_to_byte_index("😀a", 2), 4);
+        assert_eq!(utf16_to_byte_index("ab", 5), 2);
+    }
+}
@@ -0,0 +1,210 @@
+use bevy::input::ButtonState;
+use bevy::prelude::KeyCode;
+
+use crate::error::BridgeError;
+
+// `KeyEvent` actions.
+const ACTION_DOWN: i32 = 0;
+const ACTION_UP: i32 = 1;
+
+// `KeyEvent` meta state flags that turn a key press into a shortcut instead of text.
+const META_CTRL_ON: i32 = 0x1000;
+const META_META_ON: i32 = 0x10000;
+
+/// Maps an android `KeyEvent.getAction` to the bevy button state.
+pub fn key_state(action: i32) -> Result<ButtonState, BridgeError> {
+    match action {
+        ACTION_DOWN => Ok(ButtonState::Pressed),
+        ACTION_UP => Ok(ButtonState::Released),
+        _ => Err(BridgeError::UnknownKeyAction(action)),
+    }
+}
+
+/// The text typed by a key press, from `KeyEvent.getUnicodeChar(metaState)`.
+///
+/// `None` for keys without text, control characters and presses with Ctrl or Meta held.
+pub fn received_character(unicode: i32, meta_state: i32) -> Option<char> {
+    if unicode <= 0 || meta_state & (META_CTRL_ON | META_META_ON) != 0 {
+        return None;
+    }
+
+    char::from_u32(unicode as u32).filter(|c| !c.is_control())
+}
+
+/// Maps an android `KEYCODE_*` to the bevy key code, `None` for keys bevy has no code for.
+pub fn key_code(keycode: i32) -> Option<KeyCode> {
+    use KeyCode::*;
+
+    let key_code = match keycode {
+        3 => WebHome,
+        4 => NavigateBackward,
+        7 => Key0,
+        8 => Key1,
+        9 => Key2,
+        10 => Key3,
+        11 => Key4,
+        12 => Key5,
+        13 => Key6,
+        14 => Key7,
+        15 => Key8,
+        16 => Key9,
+        17 => Asterisk,
+        19 => Up,
+        20 => Down,
+        21 => Left,
+        22 => Right,
+        24 => VolumeUp,
+        25 => VolumeDown,
+        26 => Power,
+        29 => A,
+        30 => B,
+        31 => C,
+        32 => D,
+        33 => E,
+        34 => F,
+        35 => G,
+        36 => H,
+        37 => I,
+        38 => J,
+        39 => K,
+        40 => L,
+        41 => M,
+        42 => N,
+        43 => O,
+        44 => P,
+        45 => Q,
+        46 => R,
+        47 => S,
+        48 => T,
+        49 => U,
+        50 => V,
+        51 => W,
+        52 => X,
+        53 => Y,
+        54 => Z,
+        55 => Comma,
+        56 => Period,
+        57 => AltLeft,
+        58 => AltRight,
+        59 => ShiftLeft,
+        60 => ShiftRight,
+        61 => Tab,
+        62 => Space,
+        64 => WebHome,
+        65 => Mail,
+        66 => Return,
+        67 => Back,
+        68 => Grave,
+        69 => Minus,
+        70 => Equals,
+        71 => BracketLeft,
+        72 => BracketRight,
+        73 => Backslash,
+        74 => Semicolon,
+        75 => Apostrophe,
+        76 => Slash,
+        77 => At,
+        81 => Plus,
+        82 => Apps,
+        84 => WebSearch,
+        85 => PlayPause,
+        86 => MediaStop,
+        87 => NextTrack,
+        88 => PrevTrack,
+        91 => Mute,
+        92 => PageUp,
+        93 => PageDown,
+        111 => Escape,
+        112 => Delete,
+        113 => ControlLeft,
+        114 => ControlRight,
+        115 => Capital,
+        116 => Scroll,
+        117 => SuperLeft,
+        118 => SuperRight,
+        120 => Sysrq,
+        121 => Pause,
+        122 => Home,
+        123 => End,
+        124 => Insert,
+        125 => NavigateForward,
+        131 => F1,
+        132 => F2,
+        133 => F3,
+        134 => F4,
+        135 => F5,
+        136 => F6,
+        137 => F7,
+        138 => F8,
+        139 => F9,
+        140 => F10,
+        141 => F11,
+        142 => F12,
+        143 => Numlock,
+        144 => Numpad0,
+        145 => Numpad1,
+        146 => Numpad2,
+        147 => Numpad3,
+        148 => Numpad4,
+        149 => Numpad5,
+        150 => Numpad6,
+        151 => Numpad7,
+        152 => Numpad8,
+        153 => Numpad9,
+        154 => NumpadDivide,
+        155 => NumpadMultiply,
+        156 => NumpadSubtract,
+        157 => NumpadAdd,
+        158 => NumpadDecimal,
+        159 => NumpadComma,
+        160 => NumpadEnter,
+        161 => NumpadEquals,
+        164 => Mute,
+        210 => Calculator,
+        213 => NoConvert,
+        214 => Convert,
+        216 => Yen,
+        217 => AbntC1,
+        218 => Kana,
+        223 => Sleep,
+        224 => Wake,
+        277 => Cut,
+        278 => Copy,
+        279 => Paste,
+        _ => return None,
+    };
+
+    Some(key_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::ButtonState;
+    use bevy::prelude::KeyCode;
+
+    use crate::error::BridgeError;
+
+    use super::{key_code, key_state, received_character};
+
+    #[test]
+    fn android_keycodes_map_to_bevy() {
+        assert_eq!(key_code(29), Some(KeyCode::A));
+        assert_eq!(key_code(54), Some(KeyCode::Z));
+        assert_eq!(key_code(7), Some(KeyCode::Key0));
+        assert_eq!(key_code(67), Some(KeyCode::Back));
+        assert_eq!(key_code(142), Some(KeyCode::F12));
+        assert_eq!(key_code(0), None);
+    }
+
+    #[test]
+    fn key_actions_and_characters() {
+        assert_eq!(key_state(0), Ok(ButtonState::Pressed));
+        assert_eq!(key_state(1), Ok(ButtonState::Released));
+        assert_eq!(key_state(2), Err(BridgeError::UnknownKeyAction(2)));
+
+        assert_eq!(received_character('a' as i32, 0), Some('a'));
+        assert_eq!(received_character('a' as i32, 0x1000), None);
+        assert_eq!(received_character('\n' as i32, 0), None);
+        assert_eq!(received_character(0, 0), None);
+    }
+}
@@ -0,0 +1,32 @@
+use std::time::{Duration, Instant};
+
+use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics};
+use bevy::prelude::*;
+
+/// Milliseconds from the host seeing the oldest input of a frame to the end of its update, only
+/// measured in frames that handle input.
+pub const INPUT_LATENCY: DiagnosticId = DiagnosticId::from_u128(0x7c1f_4a52_93d0_4c6e_b0a1_5e2d_8f36_c9b4);
+
+/// The input handled by the current update.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct InputLatency {
+    /// When the host saw the oldest input of this update, `None` if there was none.
+    pub oldest_input: Option<Instant>,
+}
+
+impl InputLatency {
+    /// How long the oldest input of this update has been waiting so far.
+    pub fn latency(&self) -> Option<Duration> {
+        self.oldest_input.map(|time| time.elapsed())
+    }
+}
+
+pub(crate) fn input_latency_diagnostic() -> Diagnostic {
+    Diagnostic::new(INPUT_LATENCY, "input_latency", 120).with_suffix("ms")
+}
+
+pub(crate) fn measure_input_latency(input_latency: Res<InputLatency>, mut diagnostics: Diagnostics) {
+    if let Some(latency) = input_latency.latency() {
+        diagnostics.add_measurement(INPUT_LATENCY, || latency.as_secs_f64() * 1000.);
+    }
+}
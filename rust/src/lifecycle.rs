@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::pacing::FrameRate;
+
+/// Where the runner is in the surface lifecycle. Kept up to date as a resource.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SurfaceState {
+    /// No surface was ever attached.
+    #[default]
+    NoSurface,
+    /// A surface was created, its size is not known yet.
+    SurfaceAttached,
+    /// The surface has a size, the app updates every frame.
+    Running,
+    /// The host destroyed the surface, it is released after one more update.
+    Suspending,
+    /// The surface was released, waiting for a new one.
+    Paused,
+    /// The game was asked to stop, the runner exits after this update.
+    Stopping,
+}
+
+/// What moves the runner from one [`SurfaceState`] to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SurfaceTransition {
+    /// `surfaceCreated`
+    Attach,
+    /// `surfaceChanged`
+    Resize,
+    /// `surfaceDestroyed`
+    Detach,
+    /// The runner dropped the destroyed surface.
+    Release,
+    /// `stopGame`
+    Stop,
+}
+
+/// Sent every time the [`SurfaceState`] changes.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceStateChanged {
+    pub from: SurfaceState,
+    pub to: SurfaceState,
+}
+
+/// What happens to the simulation while the activity is paused (between `onPause` and `onResume`).
+///
+/// Either way `ApplicationLifetime::Suspended` / `Resumed` are sent once, whether the activity or
+/// the surface goes first, and one update runs right after `onPause` so systems can react to it.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub enum PausePolicy {
+    /// Stop updating and pause virtual [`Time`] until the activity resumes.
+    #[default]
+    Freeze,
+    /// Keep simulating, at this rate instead of the [`FramePacing`](crate::pacing::FramePacing).
+    LowTickRate(FrameRate),
+}
+
+/// How long the size of a running surface has to stay the same before the window follows it, so a
+/// burst of resizes (dragging the split-screen divider) ends up as one
+/// [`WindowResized`](bevy::window::WindowResized). The first size of a new surface is used right
+/// away.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeDebounce(pub Duration);
+
+impl Default for ResizeDebounce {
+    fn default() -> Self {
+        ResizeDebounce(Duration::from_millis(100))
+    }
+}
+
+impl SurfaceState {
+    /// The state `transition` leads to, or `None` if it is not allowed in this state.
+    pub fn next(self, transition: SurfaceTransition) -> Option<SurfaceState> {
+        use SurfaceState::*;
+        use SurfaceTransition::*;
+
+        match (self, transition) {
+            (NoSurface | Paused, Attach) => Some(SurfaceAttached),
+            (SurfaceAttached | Running, Resize) => Some(Running),
+            (SurfaceAttached | Running, Detach) => Some(Suspending),
+            (Suspending, Release) => Some(Paused),
+            (Stopping, Stop) => None,
+            (_, Stop) => Some(Stopping),
+            _ => None,
+        }
+    }
+
+    /// Whether the app is updated in this state.
+    pub fn should_update(self) -> bool {
+        matches!(self, SurfaceState::Running | SurfaceState::Suspending | SurfaceState::Stopping)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SurfaceState::*;
+    use super::SurfaceTransition::*;
+
+    #[test]
+    fn surface_lifecycle_transitions() {
+        assert_eq!(NoSurface.next(Attach), Some(SurfaceAttached));
+        assert_eq!(SurfaceAttached.next(Resize), Some(Running));
+        assert_eq!(Running.next(Resize), Some(Running));
+        assert_eq!(Running.next(Detach), Some(Suspending));
+        assert_eq!(Suspending.next(Release), Some(Paused));
+        assert_eq!(Paused.next(Attach), Some(SurfaceAttached));
+        assert_eq!(Paused.next(Stop), Some(Stopping));
+    }
+
+    #[test]
+    fn illegal_transitions_are_rejected() {
+        assert_eq!(NoSurface.next(Resize), None);
+        assert_eq!(NoSurface.next(Detach), None);
+        assert_eq!(Running.next(Attach), None);
+        assert_eq!(Suspending.next(Resize), None);
+        assert_eq!(Paused.next(Detach), None);
+        assert_eq!(Stopping.next(Attach), None);
+        assert_eq!(Stopping.next(Stop), None);
+    }
+}
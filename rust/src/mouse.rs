@@ -0,0 +1,97 @@
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+
+use crate::error::BridgeError;
+use crate::touch::touch_phase;
+
+// `MotionEvent` actions only sent by pointing devices.
+const ACTION_MOVE: i32 = 2;
+const ACTION_HOVER_MOVE: i32 = 7;
+const ACTION_SCROLL: i32 = 8;
+const ACTION_HOVER_ENTER: i32 = 9;
+const ACTION_HOVER_EXIT: i32 = 10;
+const ACTION_BUTTON_PRESS: i32 = 11;
+const ACTION_BUTTON_RELEASE: i32 = 12;
+
+/// What a mouse or touchpad `MotionEvent` did, besides moving the cursor to its position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseAction {
+    /// The cursor entered the surface.
+    Entered,
+    /// The cursor left the surface.
+    Left,
+    /// The cursor moved, `delta` is the relative motion reported by the device.
+    Moved {
+        delta: Vec2,
+    },
+    Button {
+        button: MouseButton,
+        state: ButtonState,
+    },
+    /// Scrolled by this many lines.
+    Wheel {
+        x: f32,
+        y: f32,
+    },
+}
+
+/// Maps a `MotionEvent.BUTTON_*` flag to the bevy button.
+pub fn mouse_button(button: i32) -> Option<MouseButton> {
+    match button {
+        0 => None,
+        1 => Some(MouseButton::Left),
+        2 => Some(MouseButton::Right),
+        4 => Some(MouseButton::Middle),
+        _ => Some(MouseButton::Other(button.trailing_zeros() as u16)),
+    }
+}
+
+/// Maps a `MotionEvent` from a mouse or touchpad.
+///
+/// Down and up are not forwarded, the buttons arrive with `ACTION_BUTTON_PRESS` /
+/// `ACTION_BUTTON_RELEASE`.
+pub fn mouse_action(action: i32, action_button: i32, delta: Vec2, scroll: Vec2) -> Result<Option<MouseAction>, BridgeError> {
+    let state = match action {
+        ACTION_MOVE | ACTION_HOVER_MOVE => return Ok(Some(MouseAction::Moved { delta })),
+        ACTION_SCROLL => return Ok(Some(MouseAction::Wheel { x: scroll.x, y: scroll.y })),
+        ACTION_HOVER_ENTER => return Ok(Some(MouseAction::Entered)),
+        ACTION_HOVER_EXIT => return Ok(Some(MouseAction::Left)),
+        ACTION_BUTTON_PRESS => ButtonState::Pressed,
+        ACTION_BUTTON_RELEASE => ButtonState::Released,
+        _ => {
+            // reject unknown actions the same way touches do
+            touch_phase(action)?;
+            return Ok(None);
+        }
+    };
+
+    Ok(mouse_button(action_button).map(|button| MouseAction::Button { button, state }))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::ButtonState;
+    use bevy::prelude::*;
+
+    use crate::error::BridgeError;
+
+    use super::{mouse_action, MouseAction};
+
+    #[test]
+    fn mouse_actions() {
+        let delta = Vec2::new(1., 2.);
+        let scroll = Vec2::new(0., -1.);
+        assert_eq!(mouse_action(7, 0, delta, scroll), Ok(Some(MouseAction::Moved { delta })));
+        assert_eq!(mouse_action(8, 0, delta, scroll), Ok(Some(MouseAction::Wheel { x: 0., y: -1. })));
+        assert_eq!(mouse_action(11, 2, delta, scroll), Ok(Some(MouseAction::Button {
+            button: MouseButton::Right,
+            state: ButtonState::Pressed,
+        })));
+        assert_eq!(mouse_action(12, 8, delta, scroll), Ok(Some(MouseAction::Button {
+            button: MouseButton::Other(3),
+            state: ButtonState::Released,
+        })));
+        assert_eq!(mouse_action(0, 0, delta, scroll), Ok(None));
+        assert_eq!(mouse_action(42, 0, delta, scroll), Err(BridgeError::UnknownMotionAction(42)));
+    }
+}
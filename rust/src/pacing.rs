@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// How often the runner updates the app.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameRate {
+    /// At most this many updates per second.
+    Fps(f64),
+    /// As fast as possible, so frames are only paced by vsync when presenting.
+    Unlimited,
+}
+
+impl FrameRate {
+    /// `fps <= 0` (or NaN) means [`FrameRate::Unlimited`].
+    pub fn from_fps(fps: f32) -> Self {
+        if fps > 0. {
+            FrameRate::Fps(fps as f64)
+        } else {
+            FrameRate::Unlimited
+        }
+    }
+
+    /// The minimum time between two updates. An `Fps` that is not positive (or NaN) is
+    /// [`FrameRate::Unlimited`], like in [`FrameRate::from_fps`].
+    pub fn frame_duration(self) -> Duration {
+        match self {
+            FrameRate::Fps(fps) => Duration::try_from_secs_f64(1.0 / fps).unwrap_or(Duration::ZERO),
+            FrameRate::Unlimited => Duration::ZERO,
+        }
+    }
+}
+
+/// Frame pacing of the runner. Change it at runtime like any other resource, or from the host with
+/// `setTargetFrameRate` / `setIdleFrameRate`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct FramePacing {
+    /// The rate while the player is interacting.
+    pub target: FrameRate,
+    /// The rate used once no input arrived for `idle_after`, `None` keeps `target`.
+    pub idle: Option<FrameRate>,
+    pub idle_after: Duration,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        FramePacing {
+            target: FrameRate::Fps(60.),
+            idle: None,
+            idle_after: Duration::from_secs(5),
+        }
+    }
+}
+
+impl FramePacing {
+    /// The rate to use when the last input arrived `since_input` ago.
+    pub fn current(&self, since_input: Duration) -> FrameRate {
+        match self.idle {
+            Some(idle) if since_input >= self.idle_after => idle,
+            _ => self.target,
+        }
+    }
+}
+
+/// When the runner updates the app while it has a surface.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateMode {
+    /// Every frame, paced by [`FramePacing`].
+    #[default]
+    Continuous,
+    /// Only when a command arrives from the host, a [`RequestRedraw`](bevy::window::RequestRedraw)
+    /// was sent during the previous update, or `max_wait` passed since the last update. Updates are
+    /// still no closer than the current [`FrameRate`] allows.
+    Reactive {
+        max_wait: Duration,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{FramePacing, FrameRate};
+
+    #[test]
+    fn frame_rate_from_fps() {
+        assert_eq!(FrameRate::from_fps(120.), FrameRate::Fps(120.));
+        assert_eq!(FrameRate::from_fps(0.), FrameRate::Unlimited);
+        assert_eq!(FrameRate::from_fps(f32::NAN), FrameRate::Unlimited);
+        assert_eq!(FrameRate::Fps(50.).frame_duration(), Duration::from_millis(20));
+        assert_eq!(FrameRate::Unlimited.frame_duration(), Duration::ZERO);
+    }
+
+    #[test]
+    fn invalid_fps_is_unlimited() {
+        assert_eq!(FrameRate::Fps(0.).frame_duration(), Duration::ZERO);
+        assert_eq!(FrameRate::Fps(-30.).frame_duration(), Duration::ZERO);
+        assert_eq!(FrameRate::Fps(f64::NAN).frame_duration(), Duration::ZERO);
+        assert_eq!(FrameRate::Fps(f64::INFINITY).frame_duration(), Duration::ZERO);
+    }
+
+    #[test]
+    fn idle_rate_after_timeout() {
+        let pacing = FramePacing {
+            target: FrameRate::Fps(120.),
+            idle: Some(FrameRate::Fps(10.)),
+            idle_after: Duration::from_secs(2),
+        };
+        assert_eq!(pacing.current(Duration::from_secs(1)), FrameRate::Fps(120.));
+        assert_eq!(pacing.current(Duration::from_secs(2)), FrameRate::Fps(10.));
+        assert_eq!(FramePacing::default().current(Duration::MAX), FrameRate::Fps(60.));
+    }
+}
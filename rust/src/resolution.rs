@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+/// The size bevy renders at. Anything but [`RenderResolution::Native`] sets the size of the
+/// surface buffers and lets the android compositor scale them to the surface, which is cheap on
+/// low-end devices. The physical size of the primary window is the internal size, its scale factor
+/// follows it so the logical size, the UI and touches stay the same.
+///
+/// Can be changed at runtime, by the game or the host with `setRenderResolution`.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub enum RenderResolution {
+    /// One buffer pixel per surface pixel.
+    #[default]
+    Native,
+    /// A fixed size in pixels, stretched to the surface. Width and height are swapped when the
+    /// surface is rotated the other way, a `0` side follows the aspect ratio of the surface.
+    Fixed { width: u32, height: u32 },
+    /// A fraction of the surface size per side, e.g. `0.5` for a quarter of the pixels.
+    Scale(f32),
+}
+
+impl RenderResolution {
+    /// The buffer size for a surface of `width` x `height` pixels.
+    pub fn buffer_size(self, width: u32, height: u32) -> UVec2 {
+        let surface_size = Vec2::new(width as f32, height as f32);
+        match self {
+            RenderResolution::Native => UVec2::new(width, height),
+            RenderResolution::Fixed { width: 0, height: 0 } => UVec2::new(width, height).max(UVec2::ONE),
+            RenderResolution::Fixed { width: fixed_width, height: 0 } => {
+                UVec2::new(fixed_width, (fixed_width as f32 * surface_size.y / surface_size.x.max(1.)).round() as u32)
+                    .max(UVec2::ONE)
+            }
+            RenderResolution::Fixed { width: 0, height: fixed_height } => {
+                UVec2::new((fixed_height as f32 * surface_size.x / surface_size.y.max(1.)).round() as u32, fixed_height)
+                    .max(UVec2::ONE)
+            }
+            RenderResolution::Fixed { width: fixed_width, height: fixed_height } => {
+                let rotated = (fixed_width > fixed_height && width < height)
+                    || (fixed_width < fixed_height && width > height);
+                match rotated {
+                    true => UVec2::new(fixed_height, fixed_width),
+                    false => UVec2::new(fixed_width, fixed_height),
+                }
+            }
+            RenderResolution::Scale(scale) => (surface_size * scale)
+                .round()
+                .as_uvec2()
+                .max(UVec2::ONE),
+        }
+    }
+}
+
+/// Buffer pixels per surface pixel, positions from the host are multiplied with it.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Deref)]
+pub(crate) struct BufferScale(pub(crate) Vec2);
+
+impl Default for BufferScale {
+    fn default() -> Self {
+        BufferScale(Vec2::ONE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::RenderResolution;
+
+    #[test]
+    fn buffer_sizes() {
+        assert_eq!(RenderResolution::Native.buffer_size(1080, 2400), UVec2::new(1080, 2400));
+        assert_eq!(RenderResolution::Fixed { width: 720, height: 1600 }.buffer_size(1080, 2400), UVec2::new(720, 1600));
+        assert_eq!(RenderResolution::Scale(0.5).buffer_size(1080, 2401), UVec2::new(540, 1201));
+        assert_eq!(RenderResolution::Scale(0.).buffer_size(1080, 2400), UVec2::ONE);
+    }
+
+    #[test]
+    fn fixed_sizes_follow_the_surface() {
+        let landscape = RenderResolution::Fixed { width: 1600, height: 720 };
+        assert_eq!(landscape.buffer_size(1080, 2400), UVec2::new(720, 1600));
+        assert_eq!(landscape.buffer_size(2400, 1080), UVec2::new(1600, 720));
+        assert_eq!(landscape.buffer_size(1000, 1000), UVec2::new(1600, 720));
+
+        assert_eq!(RenderResolution::Fixed { width: 540, height: 0 }.buffer_size(1080, 2400), UVec2::new(540, 1200));
+        assert_eq!(RenderResolution::Fixed { width: 0, height: 540 }.buffer_size(2400, 1080), UVec2::new(1200, 540));
+        assert_eq!(RenderResolution::Fixed { width: 0, height: 0 }.buffer_size(1080, 2400), UVec2::new(1080, 2400));
+    }
+}
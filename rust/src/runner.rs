@@ -0,0 +1,1802 @@
+use std::time::{Duration, Instant};
+
+use bevy::app::{AppExit, PluginsState};
+use bevy::diagnostic::RegisterDiagnostic;
+use bevy::ecs::event::ManualEventReader;
+use bevy::ecs::system::{SystemParam, SystemState};
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::mouse::{MouseButtonInput, MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::input::touchpad::{TouchpadMagnify, TouchpadRotate};
+use bevy::prelude::*;
+use bevy::render::pipelined_rendering::RenderExtractApp;
+use bevy::tasks::tick_global_task_pools_on_main_thread;
+use bevy::window::{ApplicationLifetime, CursorEntered, CursorLeft, CursorMoved, Ime, RawHandleWrapper, ReceivedCharacter, RequestRedraw, Window, WindowBackendScaleFactorChanged, WindowCloseRequested, WindowCreated, WindowDestroyed, WindowResized, WindowScaleFactorChanged};
+
+use crate::back::{BackPressConsumed, BackPressed};
+use crate::command::{Cmd, CMD_QUEUE, CmdQueue, SurfaceRelease};
+use crate::error::{BridgeError, report_error};
+use crate::format::{apply_surface_composition, forward_surface_format_requests, RequestedSurfaceFormat, SurfaceComposition, SurfaceFormat};
+use crate::gesture::TouchGestures;
+use crate::hit_test::{HitRegions, SharedHitRegions, TouchHitTest, update_hit_regions};
+use crate::ime::forward_ime_requests;
+use crate::latency::{InputLatency, input_latency_diagnostic, measure_input_latency};
+use crate::lifecycle::{PausePolicy, ResizeDebounce, SurfaceState, SurfaceStateChanged, SurfaceTransition};
+use crate::mouse::MouseAction;
+use crate::pacing::{FramePacing, UpdateMode};
+use crate::resolution::{BufferScale, RenderResolution};
+use crate::safe_area::{apply_safe_area_padding, SafeAreaInsets, SafeAreaInsetsChanged};
+use crate::surface::Surface;
+use crate::touch::TouchDetails;
+
+/// Replaces bevy's winit runner with one driven by the [`Cmd`]s sent from the host.
+///
+/// Commands are read from the [`CmdQueue`] resource if the app has one, otherwise from the global
+/// queue used by the C API.
+pub struct SurfaceRunnerPlugin;
+
+/// A size reported by `surfaceChanged`.
+#[derive(Debug, Clone, Copy)]
+struct PendingResize {
+    width: u32,
+    height: u32,
+    scale_factor: f64,
+    format: SurfaceFormat,
+    at: Instant,
+}
+
+#[derive(Debug)]
+struct WinitWindow {
+    entity: Option<Entity>,
+    state: SurfaceState,
+    started: bool,
+    /// The app was last told it runs, with `Started` or `Resumed`.
+    running: bool,
+    window: Option<Box<dyn Surface>>,
+    last_update: Instant,
+    last_input: Instant,
+    /// Dropped once the destroyed surface is released, for hosts that do not wait for it.
+    surface_release: Option<SurfaceRelease>,
+    /// The last size the host reported, until the window follows it.
+    pending_resize: Option<PendingResize>,
+    /// The size the window follows now.
+    last_resize: Option<PendingResize>,
+    /// The [`RenderResolution`] the window follows now.
+    render_resolution: RenderResolution,
+    /// Buffer pixels per surface pixel.
+    buffer_scale: Vec2,
+    /// As sent by the host, in physical pixels.
+    safe_area_insets: SafeAreaInsets,
+    /// When the host saw the oldest input since the last update.
+    oldest_input: Option<Instant>,
+    /// Between `onPause` and `onResume`.
+    activity_paused: bool,
+    /// Virtual time was paused by `onPause`, not by the app.
+    time_paused: bool,
+    /// A command arrived since the last update.
+    woken: bool,
+    /// A [`RequestRedraw`] was sent during the last update.
+    redraw_requested: bool,
+}
+
+impl WinitWindow {
+    /// Notes input the host saw at `time`.
+    fn input(&mut self, time: Instant) {
+        self.last_input = Instant::now();
+        self.oldest_input = Some(self.oldest_input.map_or(time, |oldest| oldest.min(time)));
+    }
+
+    /// Called right before an update, hands it the input that arrived since the last one.
+    fn begin_update(&mut self, world: &mut World) {
+        self.last_update = Instant::now();
+        world.insert_resource(InputLatency { oldest_input: self.oldest_input.take() });
+    }
+
+    /// Moves to the state `transition` leads to and reports it with a [`SurfaceStateChanged`] if
+    /// it is a different one, e.g. not for a resize while running. Stays put if the transition is
+    /// not allowed in the current state.
+    fn transition(&mut self, world: &mut World, transition: SurfaceTransition) -> Result<(), BridgeError> {
+        let from = self.state;
+        let to = self.check(transition)?;
+        if from == to {
+            return Ok(());
+        }
+
+        debug!("surface state {:?} -> {:?}", from, to);
+        self.state = to;
+        world.insert_resource(to);
+        world.send_event(SurfaceStateChanged { from, to });
+        Ok(())
+    }
+
+    /// Sends the [`ApplicationLifetime`] of the surface and the activity together, only when it
+    /// changes: the app runs while it has a surface and the activity is not paused.
+    fn update_lifetime(&mut self, world: &mut World) {
+        let running = matches!(self.state, SurfaceState::SurfaceAttached | SurfaceState::Running)
+            && !self.activity_paused;
+        if running == self.running {
+            return;
+        }
+        self.running = running;
+
+        let lifetime = match (running, self.started) {
+            (false, _) => ApplicationLifetime::Suspended,
+            (true, false) => ApplicationLifetime::Started,
+            (true, true) => ApplicationLifetime::Resumed,
+        };
+        self.started |= running;
+        world.send_event(lifetime);
+    }
+
+    /// Whether updates are stopped because the activity is paused. The surface is still released
+    /// and the game can still be stopped.
+    fn is_frozen(&self, pause_policy: PausePolicy) -> bool {
+        self.activity_paused
+            && pause_policy == PausePolicy::Freeze
+            && !matches!(self.state, SurfaceState::Suspending | SurfaceState::Stopping)
+    }
+
+    /// How long to wait between two updates.
+    fn frame_duration(&self, frame_pacing: &FramePacing, pause_policy: PausePolicy) -> Duration {
+        match pause_policy {
+            PausePolicy::LowTickRate(rate) if self.activity_paused => rate.frame_duration(),
+            _ => frame_pacing.current(self.last_input.elapsed()).frame_duration(),
+        }
+    }
+
+    /// How long until the pending resize settles, `None` without one.
+    fn resize_due_in(&self, resize_debounce: ResizeDebounce) -> Option<Duration> {
+        self.pending_resize.map(|resize| resize_debounce.0.saturating_sub(resize.at.elapsed()))
+    }
+
+    /// Whether the app should be updated in this loop iteration. Reactive updates are still at
+    /// least `frame_duration` apart.
+    fn should_update_now(&self, update_mode: UpdateMode, frame_duration: Duration) -> bool {
+        match update_mode {
+            UpdateMode::Continuous => true,
+            UpdateMode::Reactive { max_wait } => {
+                let since_last_update = self.last_update.elapsed();
+                self.state == SurfaceState::Stopping
+                    || since_last_update >= max_wait
+                    || (self.woken || self.redraw_requested) && since_last_update >= frame_duration
+            }
+        }
+    }
+
+    /// The state `transition` would lead to.
+    fn check(&self, transition: SurfaceTransition) -> Result<SurfaceState, BridgeError> {
+        self.state.next(transition).ok_or(BridgeError::InvalidTransition {
+            transition,
+            state: self.state,
+        })
+    }
+}
+
+
+impl Plugin for SurfaceRunnerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SurfaceStateChanged>()
+            .add_event::<TouchDetails>()
+            .add_event::<BackPressed>()
+            .add_event::<SafeAreaInsetsChanged>()
+            .init_resource::<BackPressConsumed>()
+            .init_resource::<SurfaceState>()
+            .init_resource::<FramePacing>()
+            .init_resource::<UpdateMode>()
+            .init_resource::<PausePolicy>()
+            .init_resource::<ResizeDebounce>()
+            .init_resource::<TouchHitTest>()
+            .init_resource::<InputLatency>()
+            .init_resource::<SurfaceFormat>()
+            .init_resource::<RequestedSurfaceFormat>()
+            .init_resource::<SurfaceComposition>()
+            .init_resource::<SafeAreaInsets>()
+            .init_resource::<RenderResolution>()
+            .init_resource::<BufferScale>()
+            .add_systems(PreUpdate, (apply_safe_area_padding, apply_surface_composition))
+            .register_diagnostic(input_latency_diagnostic())
+            .add_systems(Last, (
+                forward_ime_requests,
+                forward_surface_format_requests,
+                update_hit_regions,
+                measure_input_latency,
+            ))
+            .set_runner(my_runner)
+        ;
+    }
+}
+
+#[derive(SystemParam)]
+struct WindowAndInputEventWriters<'w> {
+    // `winit` `WindowEvent`s
+    window_close_requested: EventWriter<'w, WindowCloseRequested>,
+    window_destroyed: EventWriter<'w, WindowDestroyed>,
+    keyboard_input: EventWriter<'w, KeyboardInput>,
+    character_input: EventWriter<'w, ReceivedCharacter>,
+    mouse_button_input: EventWriter<'w, MouseButtonInput>,
+    mouse_wheel_input: EventWriter<'w, MouseWheel>,
+    touch_input: EventWriter<'w, TouchInput>,
+    ime_input: EventWriter<'w, Ime>,
+    cursor_moved: EventWriter<'w, CursorMoved>,
+    cursor_entered: EventWriter<'w, CursorEntered>,
+    cursor_left: EventWriter<'w, CursorLeft>,
+    // `winit` `DeviceEvent`s
+    mouse_motion: EventWriter<'w, MouseMotion>,
+    app_exit: EventWriter<'w, AppExit>,
+}
+
+fn my_runner(mut app: App) {
+    let cmd_queue = app.world.get_resource::<CmdQueue>()
+        .or_else(|| CMD_QUEUE.get())
+        .expect("no CmdQueue resource and init_command_queue was not called")
+        .clone();
+    *cmd_queue.running_loop.lock().unwrap() = true;
+
+    if app.plugins_state() == PluginsState::Ready {
+        // If we're already ready, we finish up now and advance one frame.
+        // This prevents black frames during the launch transition on iOS.
+        app.finish();
+        app.cleanup();
+        app.update();
+    }
+
+
+    #[allow(clippy::type_complexity)]
+    let mut create_window_system_state: SystemState<(
+        Commands,
+        Query<(Entity, &mut Window), Added<Window>>,
+        EventWriter<WindowCreated>,
+    )> = SystemState::from_world(&mut app.world);
+
+
+    let mut event_writer_system_state: SystemState<(
+        WindowAndInputEventWriters,
+        Query<&mut Window>,
+    )> = SystemState::new(&mut app.world);
+
+
+    let mut app_exit_event_reader = ManualEventReader::<AppExit>::default();
+    let mut redraw_event_reader = ManualEventReader::<RequestRedraw>::default();
+
+    let cmd_receiver = cmd_queue.receiver.lock().unwrap();
+    app.world.insert_resource(SharedHitRegions(cmd_queue.hit_regions.clone()));
+
+    let mut winit_window = WinitWindow {
+        entity: None,
+        state: SurfaceState::NoSurface,
+        started: false,
+        running: false,
+        window: None,
+        last_update: Instant::now(),
+        last_input: Instant::now(),
+        surface_release: None,
+        pending_resize: None,
+        last_resize: None,
+        render_resolution: RenderResolution::Native,
+        buffer_scale: Vec2::ONE,
+        safe_area_insets: SafeAreaInsets::default(),
+        oldest_input: None,
+        activity_paused: false,
+        time_paused: false,
+        woken: false,
+        redraw_requested: false,
+    };
+
+    let mut quit = false;
+
+    let mut event_handler = |event: Cmd, app: &mut App, winit_window: &mut WinitWindow| {
+        // info!("handle event: {:?}", event);
+        winit_window.woken = true;
+
+        if app.plugins_state() != PluginsState::Cleaned {
+            if app.plugins_state() != PluginsState::Ready {
+                tick_global_task_pools_on_main_thread();
+            } else {
+                app.finish();
+                app.cleanup();
+            }
+        }
+
+        match event {
+            Cmd::SurfaceCreated(surface) => {
+                if winit_window.state == SurfaceState::Suspending {
+                    // a TextureView can hand out its next surface before the game let go of the last
+                    release_surface(app, winit_window, &cmd_queue);
+                }
+
+                if let Err(err) = winit_window.check(SurfaceTransition::Attach) {
+                    report_error(err);
+                    return;
+                }
+
+                let (mut commands,
+                    mut win_query,
+                    mut win_evt_writer,
+                ) = create_window_system_state.get_mut(&mut app.world);
+
+                if let Some(window_entity) = &winit_window.entity {
+                    let win_entity = *window_entity;
+
+                    if let Some(raw_handle) = surface.raw_handle() {
+                        commands
+                            .entity(win_entity)
+                            .insert(raw_handle);
+                    }
+
+                    winit_window.window = Some(surface);
+                    win_evt_writer.send(WindowCreated { window: win_entity });
+                } else if let Err(err) = create_windows(
+                    commands,
+                    win_query.iter_mut(),
+                    win_evt_writer,
+                    winit_window,
+                    surface,
+                ) {
+                    report_error(err);
+                    return;
+                }
+
+                create_window_system_state.apply(&mut app.world);
+                let _ = winit_window.transition(&mut app.world, SurfaceTransition::Attach);
+                winit_window.update_lifetime(&mut app.world);
+            }
+
+            Cmd::SurfaceChanged { width, height, scale_factor, format } => {
+                let first_size = winit_window.state == SurfaceState::SurfaceAttached;
+                if let Err(err) = winit_window.transition(&mut app.world, SurfaceTransition::Resize) {
+                    report_error(err);
+                    return;
+                }
+
+                winit_window.pending_resize = Some(PendingResize {
+                    width,
+                    height,
+                    scale_factor,
+                    format,
+                    at: Instant::now(),
+                });
+                if first_size {
+                    // nothing can be rendered before, don't wait for more
+                    apply_resize(app, winit_window);
+                }
+            }
+            Cmd::SurfaceDestroyed(release) => {
+                if let Err(err) = winit_window.transition(&mut app.world, SurfaceTransition::Detach) {
+                    report_error(err);
+                    // nothing to release, but the host is waiting for it.
+                    cmd_queue.notify_surface_destroyed_handled();
+                    return;
+                }
+                winit_window.surface_release = release;
+
+                // the next surface reports its own size
+                winit_window.pending_resize = None;
+                winit_window.last_resize = None;
+
+                let (mut event_writers,
+                    _,
+                ) = event_writer_system_state.get_mut(&mut app.world);
+
+                if let Some(window_entity) = winit_window.entity {
+                    event_writers.window_destroyed.send(WindowDestroyed {
+                        window: window_entity,
+                    });
+                }
+
+                winit_window.update_lifetime(&mut app.world);
+            }
+
+            Cmd::StopGame => {
+                if winit_window.state == SurfaceState::Suspending {
+                    // the host is waiting for the destroyed surface to be released.
+                    release_surface(app, winit_window, &cmd_queue);
+                }
+
+                if let Err(err) = winit_window.transition(&mut app.world, SurfaceTransition::Stop) {
+                    report_error(err);
+                    return;
+                }
+
+                let (mut event_writers,
+                    _,
+                ) = event_writer_system_state.get_mut(&mut app.world);
+
+                if let Some(window_entity) = winit_window.entity {
+                    event_writers.window_close_requested.send(WindowCloseRequested {
+                        window: window_entity,
+                    });
+                }
+
+                event_writers.app_exit.send(AppExit);
+            }
+            Cmd::TouchEvents(mut samples) => {
+                winit_window.input(samples.iter().map(|sample| sample.time).min().unwrap_or_else(Instant::now));
+
+                let (mut event_writers,
+                    windows,
+                ) = event_writer_system_state.get_mut(&mut app.world);
+
+                let host_to_logical = host_to_logical(winit_window, &windows);
+                for sample in &mut samples {
+                    sample.input.position *= host_to_logical;
+                }
+
+                event_writers
+                    .touch_input
+                    .send_batch(samples.iter().map(|sample| sample.input));
+
+                app.world.send_event_batch(samples.iter().map(|sample| TouchDetails {
+                    id: sample.input.id,
+                    size: sample.size,
+                    time: sample.time,
+                }));
+
+                send_touch_gestures(app, samples.iter().map(|sample| sample.input));
+            }
+            Cmd::MouseEvent { position, action, time } => {
+                winit_window.input(time);
+                let position = position * winit_window.buffer_scale;
+
+                let Some(window) = winit_window.entity else {
+                    return;
+                };
+
+                let (mut event_writers,
+                    mut windows,
+                ) = event_writer_system_state.get_mut(&mut app.world);
+
+                let Ok(mut win) = windows.get_mut(window) else {
+                    return;
+                };
+
+                if action == MouseAction::Left {
+                    win.set_physical_cursor_position(None);
+                    event_writers.cursor_left.send(CursorLeft { window });
+                    return;
+                }
+
+                if action == MouseAction::Entered {
+                    event_writers.cursor_entered.send(CursorEntered { window });
+                }
+
+                if win.physical_cursor_position() != Some(position) {
+                    win.set_physical_cursor_position(Some(position.as_dvec2()));
+                    event_writers.cursor_moved.send(CursorMoved {
+                        window,
+                        position: win.cursor_position().unwrap_or(position),
+                    });
+                }
+
+                match action {
+                    MouseAction::Moved { delta } if delta != Vec2::ZERO => {
+                        event_writers.mouse_motion.send(MouseMotion { delta });
+                    }
+                    MouseAction::Button { button, state } => {
+                        event_writers.mouse_button_input.send(MouseButtonInput { button, state, window });
+                    }
+                    MouseAction::Wheel { x, y } => {
+                        event_writers.mouse_wheel_input.send(MouseWheel { unit: MouseScrollUnit::Line, x, y, window });
+                    }
+                    _ => {}
+                }
+            }
+            Cmd::ImePreedit { value, cursor, time } => {
+                winit_window.input(time);
+
+                let Some(window) = winit_window.entity else {
+                    return;
+                };
+
+                let (mut event_writers,
+                    _,
+                ) = event_writer_system_state.get_mut(&mut app.world);
+
+                event_writers.ime_input.send(Ime::Preedit { window, value, cursor });
+            }
+            Cmd::ImeCommit { value, time } => {
+                winit_window.input(time);
+
+                let Some(window) = winit_window.entity else {
+                    return;
+                };
+
+                let (mut event_writers,
+                    _,
+                ) = event_writer_system_state.get_mut(&mut app.world);
+
+                event_writers
+                    .character_input
+                    .send_batch(value.chars().map(|char| ReceivedCharacter { window, char }));
+                event_writers.ime_input.send(Ime::Commit { window, value });
+            }
+            Cmd::KeyEvent { scan_code, key_code, state, character, time } => {
+                winit_window.input(time);
+
+                let Some(window) = winit_window.entity else {
+                    return;
+                };
+
+                let (mut event_writers,
+                    _,
+                ) = event_writer_system_state.get_mut(&mut app.world);
+
+                event_writers.keyboard_input.send(KeyboardInput {
+                    scan_code,
+                    key_code,
+                    state,
+                    window,
+                });
+
+                if let Some(char) = character {
+                    event_writers.character_input.send(ReceivedCharacter { window, char });
+                }
+            }
+            Cmd::GamepadEvents { events, time } => {
+                winit_window.input(time);
+
+                app.world.send_event_batch(events);
+            }
+            Cmd::SafeAreaInsets(insets) => {
+                winit_window.safe_area_insets = insets;
+                update_safe_area_insets(app, winit_window);
+            }
+            Cmd::OnResume => {
+                if !winit_window.activity_paused {
+                    return;
+                }
+                winit_window.activity_paused = false;
+
+                if std::mem::take(&mut winit_window.time_paused) {
+                    if let Some(mut time) = app.world.get_resource_mut::<Time<Virtual>>() {
+                        time.unpause();
+                    }
+                }
+
+                winit_window.update_lifetime(&mut app.world);
+            }
+            Cmd::OnPause => {
+                if winit_window.activity_paused {
+                    return;
+                }
+                winit_window.activity_paused = true;
+
+                let pause_policy = *app.world.resource::<PausePolicy>();
+                if pause_policy == PausePolicy::Freeze {
+                    if let Some(mut time) = app.world.get_resource_mut::<Time<Virtual>>() {
+                        // a game paused by the app stays paused after onResume
+                        winit_window.time_paused = !time.is_paused();
+                        time.pause();
+                    }
+                }
+
+                winit_window.update_lifetime(&mut app.world);
+
+                if winit_window.state.should_update() && app.plugins_state() == PluginsState::Cleaned {
+                    // let the app see it is suspended before it is frozen
+                    winit_window.begin_update(&mut app.world);
+                    app.update();
+                }
+            }
+            Cmd::SetTargetFrameRate(rate) => {
+                app.world.resource_mut::<FramePacing>().target = rate;
+            }
+            Cmd::SetIdleFrameRate { rate, idle_after } => {
+                let mut frame_pacing = app.world.resource_mut::<FramePacing>();
+                frame_pacing.idle = rate;
+                frame_pacing.idle_after = idle_after;
+            }
+            Cmd::SetUpdateMode(update_mode) => {
+                app.world.insert_resource(update_mode);
+            }
+            Cmd::SetRenderResolution(render_resolution) => {
+                app.world.insert_resource(render_resolution);
+                // the input that follows is mapped to the new resolution
+                apply_render_resolution(app, winit_window);
+            }
+            Cmd::RedrawNeeded(completion) => {
+                if winit_window.state == SurfaceState::Running && app.plugins_state() == PluginsState::Cleaned {
+                    // the frame must match the surface, e.g. right after a rotation
+                    apply_resize(app, winit_window);
+                    winit_window.begin_update(&mut app.world);
+                    app.update();
+                    if app.get_sub_app(RenderExtractApp).is_ok() {
+                        // with pipelined rendering the frame is rendered while the next update runs,
+                        // the next update waits for it to finish.
+                        app.update();
+                    }
+                }
+                // nothing to draw in the other states
+                completion.complete();
+            }
+            Cmd::BackPressed { completion, time } => {
+                winit_window.input(time);
+
+                let pause_policy = *app.world.resource::<PausePolicy>();
+                let consumed = if winit_window.state.should_update()
+                    && !winit_window.is_frozen(pause_policy)
+                    && app.plugins_state() == PluginsState::Cleaned {
+                    app.world.insert_resource(BackPressConsumed(false));
+                    app.world.send_event(BackPressed);
+                    winit_window.begin_update(&mut app.world);
+                    app.update();
+                    app.world.resource::<BackPressConsumed>().0
+                } else {
+                    // nobody would see it
+                    false
+                };
+                completion.complete_with(consumed);
+            }
+        }
+    };
+
+
+    while !quit {
+
+        //drain events
+        while let Ok(event) = cmd_receiver.try_recv() {
+            event_handler(event, &mut app, &mut winit_window);
+        }
+
+        if winit_window.resize_due_in(*app.world.resource::<ResizeDebounce>()) == Some(Duration::ZERO) {
+            apply_resize(&mut app, &mut winit_window);
+        }
+        apply_render_resolution(&mut app, &mut winit_window);
+
+        {
+            // handle app update after drain events
+            let pause_policy = *app.world.resource::<PausePolicy>();
+            if app.plugins_state() == PluginsState::Cleaned
+                && winit_window.state.should_update()
+                && !winit_window.is_frozen(pause_policy) {
+                if winit_window.state == SurfaceState::Suspending {
+                    release_surface(&mut app, &mut winit_window, &cmd_queue);
+                } else if winit_window.should_update_now(
+                    *app.world.resource::<UpdateMode>(),
+                    winit_window.frame_duration(app.world.resource::<FramePacing>(), pause_policy),
+                ) {
+                    winit_window.begin_update(&mut app.world);
+                    winit_window.woken = false;
+                    // info!("update app");
+                    app.update();
+
+                    winit_window.redraw_requested = app.world.get_resource::<Events<RequestRedraw>>()
+                        .is_some_and(|redraw_events| redraw_event_reader.read(redraw_events).last().is_some());
+                }
+            }
+
+            if let Some(app_exit_events) = app.world.get_resource::<Events<AppExit>>() {
+                if app_exit_event_reader.read(app_exit_events).last().is_some() {
+                    quit = true;
+                }
+            }
+        }
+
+        if !quit {
+            // waiting for events
+            let pause_policy = *app.world.resource::<PausePolicy>();
+            let event = if winit_window.state.should_update() && !winit_window.is_frozen(pause_policy) {
+                let frame_duration = winit_window.frame_duration(app.world.resource::<FramePacing>(), pause_policy);
+                let wait_duration = match *app.world.resource::<UpdateMode>() {
+                    UpdateMode::Reactive { max_wait } if !winit_window.woken && !winit_window.redraw_requested => max_wait,
+                    _ => frame_duration,
+                };
+                let since_last_update = Instant::now().checked_duration_since(winit_window.last_update)
+                    .unwrap_or_else(|| Duration::from_secs(0));
+                // info!("since_last_update: {:?}, wait_duration: {:?}", since_last_update,wait_duration);
+                let mut next_wait_duration = wait_duration
+                    .checked_sub(since_last_update)
+                    .unwrap_or_else(|| Duration::from_secs(0));
+                if let Some(resize_due_in) = winit_window.resize_due_in(*app.world.resource::<ResizeDebounce>()) {
+                    next_wait_duration = next_wait_duration.min(resize_due_in);
+                }
+                // info!("next_wait_duration: {:?}", next_wait_duration);
+                cmd_receiver.recv_timeout(next_wait_duration).ok()
+            } else {
+                // nothing to update until the host sends a command
+                cmd_receiver.recv().ok()
+            };
+
+            if let Some(event) = event {
+                // info!("event: {:?}", event);
+                event_handler(event, &mut app, &mut winit_window);
+            }
+        }
+    };
+
+    *cmd_queue.hit_regions.write().unwrap() = HitRegions::Nothing;
+    drop(cmd_receiver);
+    cmd_queue.stop_running();
+}
+
+/// Drops the destroyed surface, runs one more update so the app sees it is gone, and wakes up the
+/// host waiting in `surfaceDestroyed`, or tells it with the [`SurfaceRelease`].
+fn release_surface(app: &mut App, winit_window: &mut WinitWindow, cmd_queue: &CmdQueue) {
+    if let Some(window_entity) = winit_window.entity {
+        app.world.entity_mut(window_entity).remove::<RawHandleWrapper>();
+    }
+    winit_window.window = None;
+    let _ = winit_window.transition(&mut app.world, SurfaceTransition::Release);
+
+    winit_window.begin_update(&mut app.world);
+    app.update();
+
+    winit_window.surface_release = None;
+    cmd_queue.notify_surface_destroyed_handled();
+}
+
+/// Resizes the buffers of the current surface if the app changed its [`RenderResolution`].
+fn apply_render_resolution(app: &mut App, winit_window: &mut WinitWindow) {
+    if winit_window.pending_resize.is_none()
+        && *app.world.resource::<RenderResolution>() != winit_window.render_resolution {
+        winit_window.pending_resize = winit_window.last_resize;
+        apply_resize(app, winit_window);
+    }
+}
+
+/// Resizes the window to the pending size of the surface and sends the events winit would.
+fn apply_resize(app: &mut App, winit_window: &mut WinitWindow) {
+    let Some(resize) = winit_window.pending_resize.take() else {
+        return;
+    };
+    winit_window.woken = true;
+    winit_window.last_resize = Some(resize);
+
+    let render_resolution = *app.world.resource::<RenderResolution>();
+    let buffer_size = render_resolution.buffer_size(resize.width, resize.height);
+    if let Some(surface) = &winit_window.window {
+        let size = (render_resolution != RenderResolution::Native).then_some(buffer_size);
+        if let Err(err) = surface.set_buffer_size(size) {
+            warn!("failed to set the buffer size to {:?}: {}", size, err);
+        }
+    }
+    winit_window.render_resolution = render_resolution;
+    winit_window.buffer_scale = buffer_size.as_vec2() / UVec2::new(resize.width, resize.height).max(UVec2::ONE).as_vec2();
+    if **app.world.resource::<BufferScale>() != winit_window.buffer_scale {
+        app.world.insert_resource(BufferScale(winit_window.buffer_scale));
+    }
+
+    let format_changed = *app.world.resource::<SurfaceFormat>() != resize.format;
+    let Some((window_entity, mut window)) = winit_window.entity
+        .and_then(|window_entity| Some((window_entity, app.world.get_mut::<Window>(window_entity)?))) else {
+        report_error(BridgeError::NoPrimaryWindow);
+        return;
+    };
+
+    // fewer buffer pixels per logical pixel, so the logical size stays that of the surface
+    let scale_factor = resize.scale_factor * winit_window.buffer_scale.min_element() as f64;
+    let scale_factor_changed = window.resolution.base_scale_factor() != scale_factor;
+    let scale_factor_overridden = window.resolution.scale_factor_override().is_some();
+    let logical_size = (window.width(), window.height());
+    if scale_factor_changed {
+        window.resolution.set_scale_factor(scale_factor);
+    }
+    window.resolution.set_physical_resolution(buffer_size.x, buffer_size.y);
+    // e.g. a new render resolution, or a resize burst that ended where it started
+    let resized = (logical_size != (window.width(), window.height())).then(|| WindowResized {
+        window: window_entity,
+        width: window.width(),
+        height: window.height(),
+    });
+
+    if scale_factor_changed {
+        app.world.send_event(WindowBackendScaleFactorChanged {
+            window: window_entity,
+            scale_factor,
+        });
+        if !scale_factor_overridden {
+            app.world.send_event(WindowScaleFactorChanged {
+                window: window_entity,
+                scale_factor,
+            });
+        }
+    }
+    if let Some(resized) = resized {
+        app.world.send_event(resized);
+    }
+
+    if format_changed {
+        app.world.insert_resource(resize.format);
+    }
+    update_safe_area_insets(app, winit_window);
+}
+
+/// Updates [`SafeAreaInsets`] with the insets from the host in logical pixels of the primary window,
+/// if they changed.
+fn update_safe_area_insets(app: &mut App, winit_window: &WinitWindow) {
+    let scale_factor = winit_window.entity
+        .and_then(|entity| app.world.get::<Window>(entity))
+        .map_or(1., |window| window.scale_factor() as f32);
+
+    let scale = winit_window.buffer_scale / scale_factor;
+    let surface_insets = winit_window.safe_area_insets;
+    let insets = SafeAreaInsets {
+        left: surface_insets.left * scale.x,
+        top: surface_insets.top * scale.y,
+        right: surface_insets.right * scale.x,
+        bottom: surface_insets.bottom * scale.y,
+    };
+    if *app.world.resource::<SafeAreaInsets>() != insets {
+        app.world.insert_resource(insets);
+        app.world.send_event(SafeAreaInsetsChanged(insets));
+    }
+}
+
+/// Turns positions from the host, in surface pixels, into logical pixels of the primary window.
+fn host_to_logical(winit_window: &WinitWindow, windows: &Query<&mut Window>) -> Vec2 {
+    let scale_factor = winit_window.entity
+        .and_then(|entity| windows.get(entity).ok())
+        .map_or(1., |window| window.scale_factor() as f32);
+    winit_window.buffer_scale / scale_factor
+}
+
+/// Feeds touches to the [`TouchGestures`] recognizer, if the app has one, and sends the gestures
+/// they make.
+fn send_touch_gestures(app: &mut App, touches: impl IntoIterator<Item = TouchInput>) {
+    let Some(mut gestures) = app.world.get_resource_mut::<TouchGestures>() else {
+        return;
+    };
+
+    let (magnify, rotate): (Vec<_>, Vec<_>) = touches
+        .into_iter()
+        .filter_map(|touch| gestures.touch(&touch))
+        .unzip();
+
+    app.world.send_event_batch(magnify.into_iter().filter(|m| *m != 0.).map(TouchpadMagnify));
+    app.world.send_event_batch(rotate.into_iter().filter(|r| *r != 0.).map(TouchpadRotate));
+}
+
+
+fn create_windows<'a>(
+    mut commands: Commands,
+    mut created_windows: impl Iterator<Item=(Entity, Mut<'a, Window>)>,
+    mut event_writer: EventWriter<WindowCreated>,
+    winit_windows: &mut WinitWindow,
+    surface: Box<dyn Surface>,
+) -> Result<(), BridgeError> {
+    let (win_entity, _) = created_windows.next().ok_or(BridgeError::NoPrimaryWindow)?;
+
+    if let Some(raw_handle) = surface.raw_handle() {
+        commands
+            .entity(win_entity)
+            .insert(raw_handle);
+    }
+
+    winit_windows.entity = Some(win_entity);
+    winit_windows.window = Some(surface);
+
+    event_writer.send(WindowCreated { window: win_entity });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+    use std::sync::{Arc, Condvar, mpsc, Mutex};
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, Instant};
+
+    use bevy::diagnostic::DiagnosticsStore;
+    use bevy::input::{ButtonState, InputPlugin};
+    use bevy::input::gamepad::GamepadButtonChangedEvent;
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy::input::mouse::{MouseButtonInput, MouseWheel};
+    use bevy::input::touchpad::TouchpadMagnify;
+    use bevy::input::touch::TouchPhase;
+    use bevy::prelude::*;
+    use bevy::window::{ApplicationLifetime, CompositeAlphaMode, CursorMoved, ExitCondition, Ime, RawHandleWrapper, ReceivedCharacter, WindowCloseRequested, WindowCreated, WindowDestroyed, WindowResized, WindowScaleFactorChanged};
+
+    use crate::back::{BackPressConsumed, BackPressed};
+    use crate::command::{Cmd, CmdQueue, SurfaceRelease};
+    use crate::format::{RequestedSurfaceFormat, set_surface_format_callback, SurfaceFormat};
+    use crate::gamepad;
+    use crate::gesture::TouchGesturePlugin;
+    use crate::hit_test::TouchHitTest;
+    use crate::ime::set_ime_callback;
+    use crate::latency::{INPUT_LATENCY, InputLatency};
+    use crate::mouse::MouseAction;
+    use crate::lifecycle::{ResizeDebounce, SurfaceState, SurfaceStateChanged};
+    use crate::pacing::{FramePacing, FrameRate, UpdateMode};
+    use crate::resolution::RenderResolution;
+    use crate::safe_area::{SafeAreaInsets, SafeAreaInsetsChanged, SafeAreaPadding};
+    use crate::surface::HeadlessSurface;
+    use crate::touch::{TouchDetails, TouchSample};
+
+    use super::SurfaceRunnerPlugin;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// What a test app saw, filled on the game thread.
+    struct Recorder<T>(Arc<(Mutex<Vec<T>>, Condvar)>);
+
+    impl<T> Recorder<T> {
+        fn push(&self, values: impl IntoIterator<Item = T>) {
+            self.0.0.lock().unwrap().extend(values);
+            self.0.1.notify_all();
+        }
+
+        fn len(&self) -> usize {
+            self.0.0.lock().unwrap().len()
+        }
+
+        /// Waits until `len` values were recorded, for updates that no command can wait for.
+        /// Returns whether they were in time.
+        fn wait_for_len(&self, len: usize) -> bool {
+            let recorded = self.0.0.lock().unwrap();
+            let (recorded, _) = self.0.1.wait_timeout_while(recorded, TIMEOUT, |recorded| recorded.len() < len).unwrap();
+            recorded.len() >= len
+        }
+    }
+
+    impl<T: Clone> Recorder<T> {
+        fn get(&self) -> Vec<T> {
+            self.0.0.lock().unwrap().clone()
+        }
+
+        fn last(&self) -> Option<T> {
+            self.0.0.lock().unwrap().last().cloned()
+        }
+    }
+
+    impl<T> Clone for Recorder<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+
+    impl<T> Default for Recorder<T> {
+        fn default() -> Self {
+            Self(Arc::default())
+        }
+    }
+
+    type Setup = Box<dyn FnOnce(&mut App) + Send>;
+
+    /// A headless app with the runner, set up here and run on its own thread by [`TestApp::spawn`].
+    struct TestApp {
+        cmd_queue: CmdQueue,
+        setup: Vec<Setup>,
+    }
+
+    impl TestApp {
+        fn new() -> Self {
+            Self::with_window(WindowPlugin::default())
+        }
+
+        fn with_window(window_plugin: WindowPlugin) -> Self {
+            let mut test_app = TestApp { cmd_queue: CmdQueue::new(), setup: Vec::new() };
+            test_app.configure(move |app| {
+                app.add_plugins(window_plugin);
+            });
+            test_app
+        }
+
+        /// Runs `configure` before the runner plugin is added.
+        fn configure(&mut self, configure: impl FnOnce(&mut App) + Send + 'static) {
+            self.setup.push(Box::new(configure));
+        }
+
+        /// Records every `E` the app's systems see.
+        fn events<E: Event + Clone>(&mut self) -> Recorder<E> {
+            let recorder = Recorder::default();
+            let events = recorder.clone();
+            self.configure(move |app| {
+                app.add_systems(Update, move |mut reader: EventReader<E>| events.push(reader.read().cloned()));
+            });
+            recorder
+        }
+
+        /// Records `sample` once per update.
+        fn sample<T: Send + 'static>(&mut self, sample: impl Fn(&World) -> T + Send + Sync + 'static) -> Recorder<T> {
+            let recorder = Recorder::default();
+            let samples = recorder.clone();
+            self.configure(move |app| {
+                app.add_systems(Update, move |world: &World| samples.push([sample(world)]));
+            });
+            recorder
+        }
+
+        /// Runs the app on its own thread and waits until the runner took commands.
+        fn spawn(self) -> RunningApp {
+            let cmd_queue = self.cmd_queue.clone();
+            let (started, on_started) = mpsc::channel();
+            let game = thread::spawn(move || {
+                let mut app = App::new();
+                app.add_plugins(MinimalPlugins);
+                for setup in self.setup {
+                    setup(&mut app);
+                }
+                app
+                    .add_plugins((InputPlugin, SurfaceRunnerPlugin))
+                    .add_systems(Startup, move || started.send(()).unwrap())
+                    .insert_resource(self.cmd_queue)
+                    .run();
+            });
+            on_started.recv_timeout(TIMEOUT).expect("runner did not start");
+            RunningApp { cmd_queue, game }
+        }
+    }
+
+    /// A [`TestApp`] running on its own thread, commands are sent through it.
+    struct RunningApp {
+        cmd_queue: CmdQueue,
+        game: JoinHandle<()>,
+    }
+
+    impl RunningApp {
+        /// Attaches a headless surface of `width` x `height` pixels and waits until it runs.
+        fn attach(&self, width: u32, height: u32, scale_factor: f64) {
+            self.send(Cmd::SurfaceCreated(Box::new(HeadlessSurface)));
+            self.send(Cmd::SurfaceChanged { width, height, scale_factor, format: SurfaceFormat::Rgbx8888 });
+            self.sync();
+        }
+
+        /// Waits until every command sent so far is handled and, if the surface runs, an update
+        /// saw them.
+        fn sync(&self) {
+            self.redraw(TIMEOUT).unwrap();
+        }
+
+        fn stop(self) {
+            self.send(Cmd::StopGame);
+            self.game.join().unwrap();
+        }
+    }
+
+    impl Deref for RunningApp {
+        type Target = CmdQueue;
+
+        fn deref(&self) -> &CmdQueue {
+            &self.cmd_queue
+        }
+    }
+
+    /// Runs an app set up by `setup` with a 100 x 200 surface attached, and returns what `setup`
+    /// returned, usually its recorders.
+    fn attached_app<R>(setup: impl FnOnce(&mut TestApp) -> R) -> (RunningApp, R) {
+        let mut test_app = TestApp::new();
+        let recorders = setup(&mut test_app);
+        let game = test_app.spawn();
+        game.attach(100, 200, 1.);
+        (game, recorders)
+    }
+
+    fn primary_window(world: &World) -> Option<&Window> {
+        world.iter_entities().find_map(|entity| entity.get::<Window>())
+    }
+
+    fn touch(phase: TouchPhase, id: u64, position: Vec2) -> TouchInput {
+        TouchInput { phase, position, force: None, id }
+    }
+
+    /// A `MotionEvent` the host saw just now with `input` as its only pointer.
+    fn touch_event(input: TouchInput) -> Cmd {
+        Cmd::TouchEvents(vec![TouchSample { input, size: 0.1, time: Instant::now() }])
+    }
+
+    fn resized(events: &Recorder<WindowResized>) -> Vec<(f32, f32)> {
+        events.get().iter().map(|e| (e.width, e.height)).collect()
+    }
+
+    #[test]
+    fn surface_lifecycle_with_headless_surface() {
+        let (game, (created, resized_events, lifetime, states, destroyed, close_requested, raw_handles)) = attached_app(|app| (
+            app.events::<WindowCreated>(),
+            app.events::<WindowResized>(),
+            app.events::<ApplicationLifetime>(),
+            app.events::<SurfaceStateChanged>(),
+            app.events::<WindowDestroyed>(),
+            app.events::<WindowCloseRequested>(),
+            app.sample(|world| world.iter_entities().filter(|entity| entity.contains::<RawHandleWrapper>()).count()),
+        ));
+
+        // resized while running, the state stays the same
+        game.send(Cmd::SurfaceChanged { width: 200, height: 100, scale_factor: 1., format: SurfaceFormat::Rgbx8888 });
+        game.sync();
+        game.destroy_surface();
+        game.attach(300, 400, 1.);
+        game.stop();
+
+        assert_eq!(created.len(), 2);
+        assert_eq!(resized(&resized_events), vec![(100., 200.), (200., 100.), (300., 400.)]);
+        assert_eq!(lifetime.get(), vec![
+            ApplicationLifetime::Started,
+            ApplicationLifetime::Suspended,
+            ApplicationLifetime::Resumed,
+        ]);
+        assert_eq!(states.get().iter().map(|e| e.to).collect::<Vec<_>>(), vec![
+            SurfaceState::SurfaceAttached,
+            SurfaceState::Running,
+            SurfaceState::Suspending,
+            SurfaceState::Paused,
+            SurfaceState::SurfaceAttached,
+            SurfaceState::Running,
+            SurfaceState::Stopping,
+        ]);
+        assert_eq!(destroyed.len(), 1);
+        assert_eq!(close_requested.len(), 1);
+        assert_eq!(raw_handles.get().into_iter().max(), Some(0));
+    }
+
+    #[test]
+    fn lifetime_follows_the_activity_and_the_surface_together() {
+        let (game, lifetime) = attached_app(|app| app.events::<ApplicationLifetime>());
+
+        // going to the background and back, as android orders it
+        game.send(Cmd::OnPause);
+        game.destroy_surface();
+        game.send(Cmd::OnResume);
+        game.attach(100, 200, 1.);
+        // paused without losing the surface
+        game.send(Cmd::OnPause);
+        game.send(Cmd::OnResume);
+        game.sync();
+        game.stop();
+
+        assert_eq!(lifetime.get(), vec![
+            ApplicationLifetime::Started,
+            ApplicationLifetime::Suspended,
+            ApplicationLifetime::Resumed,
+            ApplicationLifetime::Suspended,
+            ApplicationLifetime::Resumed,
+        ]);
+    }
+
+    #[test]
+    fn surfaces_can_be_released_asynchronously() {
+        let mut test_app = TestApp::new();
+        let (resized_events, lifetime, states, destroyed) = (
+            test_app.events::<WindowResized>(),
+            test_app.events::<ApplicationLifetime>(),
+            test_app.events::<SurfaceStateChanged>(),
+            test_app.events::<WindowDestroyed>(),
+        );
+
+        // no game loop, nothing holds the surface
+        let (released, on_released) = mpsc::channel();
+        let release = |id| {
+            let released = released.clone();
+            SurfaceRelease::new(move || released.send(id).unwrap())
+        };
+        test_app.cmd_queue.destroy_surface_async(release(0));
+        assert_eq!(on_released.try_recv(), Ok(0));
+
+        let game = test_app.spawn();
+        game.attach(100, 200, 1.);
+        game.destroy_surface_async(release(1));
+        assert_eq!(on_released.recv_timeout(TIMEOUT), Ok(1));
+        assert_eq!(states.last().map(|e| e.to), Some(SurfaceState::Paused));
+
+        game.attach(300, 400, 1.);
+        game.stop();
+
+        assert_eq!(resized(&resized_events), vec![(100., 200.), (300., 400.)]);
+        assert_eq!(lifetime.get(), vec![
+            ApplicationLifetime::Started,
+            ApplicationLifetime::Suspended,
+            ApplicationLifetime::Resumed,
+        ]);
+        assert_eq!(destroyed.len(), 1);
+    }
+
+    #[test]
+    fn a_new_surface_can_follow_an_unreleased_one() {
+        let (game, (created, states)) = attached_app(|app| (app.events::<WindowCreated>(), app.events::<SurfaceStateChanged>()));
+
+        let (released, on_released) = mpsc::channel();
+        game.destroy_surface_async(SurfaceRelease::new(move || released.send(()).unwrap()));
+        game.attach(300, 400, 1.);
+        assert_eq!(on_released.recv_timeout(TIMEOUT), Ok(()));
+        game.stop();
+
+        assert_eq!(created.len(), 2);
+        assert_eq!(states.get().iter().map(|e| e.to).collect::<Vec<_>>(), vec![
+            SurfaceState::SurfaceAttached,
+            SurfaceState::Running,
+            SurfaceState::Suspending,
+            SurfaceState::Paused,
+            SurfaceState::SurfaceAttached,
+            SurfaceState::Running,
+            SurfaceState::Stopping,
+        ]);
+    }
+
+    #[test]
+    fn resize_bursts_are_debounced() {
+        let (game, (resized_events, lifetime)) = attached_app(|app| {
+            // never settles by itself, only the redraw applies the burst
+            app.configure(|app| {
+                app.insert_resource(ResizeDebounce(Duration::from_secs(3600)));
+            });
+            (app.events::<WindowResized>(), app.events::<ApplicationLifetime>())
+        });
+
+        for width in [110, 120, 130] {
+            game.send(Cmd::SurfaceChanged { width, height: 200, scale_factor: 1., format: SurfaceFormat::Rgbx8888 });
+        }
+        // runs an update without applying the pending resize
+        game.back_pressed(Instant::now(), TIMEOUT).unwrap();
+        let during_burst = resized(&resized_events);
+        game.sync();
+        // ends where it started, nothing to resize
+        for width in [150, 130] {
+            game.send(Cmd::SurfaceChanged { width, height: 200, scale_factor: 1., format: SurfaceFormat::Rgbx8888 });
+        }
+        game.sync();
+        game.stop();
+
+        assert_eq!(during_burst, vec![(100., 200.)]);
+        assert_eq!(resized(&resized_events), vec![(100., 200.), (130., 200.)]);
+        assert_eq!(lifetime.get(), vec![ApplicationLifetime::Started]);
+    }
+
+    #[test]
+    fn render_resolution_scales_the_window() {
+        let mut test_app = TestApp::new();
+        test_app.configure(|app| {
+            app.insert_resource(RenderResolution::Scale(0.5));
+        });
+        let (scale_factors, resized_events, touches) = (
+            test_app.events::<WindowScaleFactorChanged>(),
+            test_app.events::<WindowResized>(),
+            test_app.events::<TouchInput>(),
+        );
+        let physical_size = test_app.sample(|world| primary_window(world).map(|window| (window.physical_width(), window.physical_height())));
+        let game = test_app.spawn();
+
+        let touch = touch(TouchPhase::Started, 0, Vec2::new(40., 80.));
+        game.attach(100, 200, 2.);
+        game.send(touch_event(touch));
+        game.sync();
+        let scaled_size = physical_size.last().flatten();
+        game.send(Cmd::SetRenderResolution(RenderResolution::Native));
+        game.send(touch_event(TouchInput { phase: TouchPhase::Ended, ..touch }));
+        game.sync();
+        game.stop();
+
+        assert_eq!(scaled_size, Some((50, 100)));
+        assert_eq!(physical_size.last().flatten(), Some((100, 200)));
+        // density 2 at half the resolution is one buffer pixel per logical pixel
+        assert_eq!(scale_factors.get().iter().map(|e| e.scale_factor).collect::<Vec<_>>(), vec![2.]);
+        assert_eq!(resized(&resized_events), vec![(50., 100.)]);
+        let positions: Vec<_> = touches.get().iter().map(|touch| touch.position).collect();
+        assert_eq!(positions, vec![Vec2::new(20., 40.), Vec2::new(20., 40.)]);
+    }
+
+    #[test]
+    fn touch_events_are_forwarded() {
+        let (game, (touches, touch_details)) = attached_app(|app| (app.events::<TouchInput>(), app.events::<TouchDetails>()));
+
+        let touch = touch(TouchPhase::Started, 3, Vec2::new(10., 20.));
+        let moved = TouchInput { phase: TouchPhase::Moved, ..touch };
+        let second = TouchInput { id: 4, ..moved };
+        let time = Instant::now();
+        game.send(Cmd::TouchEvents(vec![TouchSample { input: touch, size: 0.1, time }]));
+        game.send(Cmd::TouchEvents(vec![
+            TouchSample { input: moved, size: 0.1, time },
+            TouchSample { input: second, size: 0.2, time },
+        ]));
+        game.sync();
+        game.stop();
+
+        assert_eq!(touches.get(), vec![touch, moved, second]);
+        assert_eq!(touch_details.get().iter().map(|e| (e.id, e.size, e.time)).collect::<Vec<_>>(), vec![
+            (3, 0.1, time),
+            (3, 0.1, time),
+            (4, 0.2, time),
+        ]);
+    }
+
+    #[test]
+    fn input_latency_is_measured_from_the_event_time() {
+        let (game, (input_latencies, latency_diagnostic)) = attached_app(|app| (
+            app.sample(|world| world.resource::<InputLatency>().latency()),
+            app.sample(|world| world.resource::<DiagnosticsStore>().get_measurement(INPUT_LATENCY).map(|m| m.value)),
+        ));
+        let before_input = input_latencies.get().into_iter().flatten().count();
+
+        // the host saw the first sample of the batch 50ms ago
+        let touch = touch(TouchPhase::Moved, 0, Vec2::ZERO);
+        let now = Instant::now();
+        game.send(Cmd::TouchEvents(vec![
+            TouchSample { input: touch, size: 0.1, time: now - Duration::from_millis(50) },
+            TouchSample { input: touch, size: 0.1, time: now },
+        ]));
+        game.sync();
+        game.stop();
+
+        let input_latencies: Vec<_> = input_latencies.get().into_iter().flatten().collect();
+        assert_eq!(before_input, 0);
+        assert_eq!(input_latencies.len(), 1);
+        assert!(input_latencies[0] >= Duration::from_millis(50));
+        assert!(latency_diagnostic.last().flatten().is_some_and(|latency| latency >= 50.));
+    }
+
+    #[test]
+    fn key_events_are_forwarded_to_the_window() {
+        let (game, (keys, characters)) = attached_app(|app| (app.events::<KeyboardInput>(), app.events::<ReceivedCharacter>()));
+
+        let key = |state, character| Cmd::KeyEvent {
+            scan_code: 29,
+            key_code: Some(KeyCode::A),
+            state,
+            character,
+            time: Instant::now(),
+        };
+        game.send(key(ButtonState::Pressed, Some('a')));
+        game.send(key(ButtonState::Released, None));
+        game.sync();
+        game.stop();
+
+        assert_eq!(keys.get().iter().map(|e| (e.key_code, e.state)).collect::<Vec<_>>(), vec![
+            (Some(KeyCode::A), ButtonState::Pressed),
+            (Some(KeyCode::A), ButtonState::Released),
+        ]);
+        assert_eq!(characters.get().iter().map(|e| e.char).collect::<String>(), "a");
+    }
+
+    #[test]
+    fn gamepad_events_are_forwarded() {
+        let (game, (gamepads, gamepad_buttons)) = attached_app(|app| (
+            app.sample(|world| world.resource::<Gamepads>().iter().count()),
+            app.events::<GamepadButtonChangedEvent>(),
+        ));
+
+        let gamepad_events = |events| Cmd::GamepadEvents { events, time: Instant::now() };
+        game.send(gamepad_events(vec![gamepad::connection_event(12, "pad".into())]));
+        game.send(gamepad_events(vec![gamepad::button_event(12, GamepadButtonType::South, true)]));
+        game.sync();
+        let connected = gamepads.last();
+        game.send(gamepad_events(vec![gamepad::disconnection_event(12)]));
+        game.sync();
+        game.stop();
+
+        assert_eq!(connected, Some(1));
+        assert_eq!(gamepads.last(), Some(0));
+        assert_eq!(gamepad_buttons.get().iter().map(|e| (e.button_type, e.value)).collect::<Vec<_>>(), vec![
+            (GamepadButtonType::South, 1.),
+        ]);
+    }
+
+    #[test]
+    fn mouse_events_move_the_cursor() {
+        let (game, (cursor_moved, mouse_buttons, wheel, cursor_position)) = attached_app(|app| (
+            app.events::<CursorMoved>(),
+            app.events::<MouseButtonInput>(),
+            app.events::<MouseWheel>(),
+            app.sample(|world| primary_window(world).and_then(|window| window.cursor_position())),
+        ));
+
+        let mouse = |x, y, action| Cmd::MouseEvent { position: Vec2::new(x, y), action, time: Instant::now() };
+        game.send(mouse(10., 20., MouseAction::Entered));
+        game.send(mouse(10., 20., MouseAction::Button { button: MouseButton::Left, state: ButtonState::Pressed }));
+        game.send(mouse(30., 40., MouseAction::Moved { delta: Vec2::new(20., 20.) }));
+        game.send(mouse(30., 40., MouseAction::Wheel { x: 0., y: 1. }));
+        game.sync();
+        let inside = cursor_position.last().flatten();
+        game.send(mouse(30., 40., MouseAction::Left));
+        game.sync();
+        game.stop();
+
+        assert_eq!(cursor_moved.get().iter().map(|e| e.position).collect::<Vec<_>>(), vec![Vec2::new(10., 20.), Vec2::new(30., 40.)]);
+        assert_eq!(mouse_buttons.get().iter().map(|e| (e.button, e.state)).collect::<Vec<_>>(), vec![(MouseButton::Left, ButtonState::Pressed)]);
+        assert_eq!(wheel.get().iter().map(|e| (e.x, e.y)).collect::<Vec<_>>(), vec![(0., 1.)]);
+        assert_eq!(inside, Some(Vec2::new(30., 40.)));
+        assert_eq!(cursor_position.last().flatten(), None);
+    }
+
+    static IME_REQUESTS: Mutex<Vec<(bool, f32, f32)>> = Mutex::new(Vec::new());
+
+    extern "C" fn record_ime_request(enabled: bool, x: f32, y: f32) {
+        IME_REQUESTS.lock().unwrap().push((enabled, x, y));
+    }
+
+    #[test]
+    fn ime_is_forwarded_both_ways() {
+        set_ime_callback(Some(record_ime_request));
+        let mut test_app = TestApp::with_window(WindowPlugin {
+            primary_window: Some(Window {
+                ime_enabled: true,
+                ime_position: Vec2::new(5., 6.),
+                ..default()
+            }),
+            ..default()
+        });
+        let (ime, characters) = (test_app.events::<Ime>(), test_app.events::<ReceivedCharacter>());
+        let game = test_app.spawn();
+
+        game.attach(100, 200, 1.);
+        game.send(Cmd::ImePreedit { value: "ab".into(), cursor: Some((2, 2)), time: Instant::now() });
+        game.send(Cmd::ImeCommit { value: "ab".into(), time: Instant::now() });
+        game.sync();
+        game.stop();
+        set_ime_callback(None);
+
+        let ime = ime.get();
+        let window = ime.first().map(|ime| match ime {
+            Ime::Enabled { window } => *window,
+            _ => panic!("expected Ime::Enabled first, got {ime:?}"),
+        }).unwrap();
+        assert_eq!(ime[1..], [
+            Ime::Preedit { window, value: "ab".into(), cursor: Some((2, 2)) },
+            Ime::Commit { window, value: "ab".into() },
+        ]);
+        assert_eq!(characters.get().iter().map(|e| e.char).collect::<String>(), "ab");
+        assert_eq!(*IME_REQUESTS.lock().unwrap(), vec![(true, 5., 6.)]);
+    }
+
+    #[test]
+    fn pinch_is_recognized_when_enabled() {
+        let pinch = |with_gestures: bool| {
+            let (game, magnify) = attached_app(|app| {
+                if with_gestures {
+                    app.configure(|app| {
+                        app.add_plugins(TouchGesturePlugin);
+                    });
+                }
+                app.events::<TouchpadMagnify>()
+            });
+
+            let touch = |phase, id, x| touch_event(touch(phase, id, Vec2::new(x, 0.)));
+            game.send(touch(TouchPhase::Started, 1, 0.));
+            game.send(touch(TouchPhase::Started, 2, 100.));
+            game.send(touch(TouchPhase::Moved, 2, 150.));
+            game.send(touch(TouchPhase::Ended, 1, 0.));
+            game.send(touch(TouchPhase::Ended, 2, 150.));
+            game.sync();
+            game.stop();
+
+            magnify.get().iter().map(|e| e.0).collect::<Vec<_>>()
+        };
+
+        assert_eq!(pinch(false), vec![]);
+        assert_eq!(pinch(true), vec![0.5]);
+    }
+
+    #[test]
+    fn density_scales_the_window_and_touches() {
+        let mut test_app = TestApp::new();
+        let (scale_factors, resized_events, touches) = (
+            test_app.events::<WindowScaleFactorChanged>(),
+            test_app.events::<WindowResized>(),
+            test_app.events::<TouchInput>(),
+        );
+        let game = test_app.spawn();
+
+        let touch = touch(TouchPhase::Started, 0, Vec2::new(30., 40.));
+        game.attach(100, 200, 2.);
+        game.send(touch_event(touch));
+        game.send(Cmd::SurfaceChanged { width: 200, height: 100, scale_factor: 2., format: SurfaceFormat::Rgbx8888 });
+        game.sync();
+        game.stop();
+
+        assert_eq!(scale_factors.get().iter().map(|e| e.scale_factor).collect::<Vec<_>>(), vec![2.]);
+        assert_eq!(resized(&resized_events), vec![(50., 100.), (100., 50.)]);
+        assert_eq!(touches.get(), vec![TouchInput { position: Vec2::new(15., 20.), ..touch }]);
+    }
+
+    #[test]
+    fn safe_area_insets_pad_marked_nodes() {
+        let mut test_app = TestApp::new();
+        test_app.configure(|app| {
+            app.world.spawn((Style::default(), SafeAreaPadding));
+        });
+        let safe_area_insets = test_app.events::<SafeAreaInsetsChanged>();
+        let padding = test_app.sample(|world| {
+            world.iter_entities()
+                .find(|entity| entity.contains::<SafeAreaPadding>())
+                .and_then(|entity| entity.get::<Style>())
+                .map(|style| style.padding)
+        });
+        let game = test_app.spawn();
+
+        let insets = SafeAreaInsets { left: 0., top: 40., right: 0., bottom: 20. };
+        game.attach(100, 200, 2.);
+        game.send(Cmd::SafeAreaInsets(insets));
+        game.sync();
+        let scaled_padding = padding.last().flatten();
+        // same insets, new density
+        game.send(Cmd::SurfaceChanged { width: 100, height: 200, scale_factor: 1., format: SurfaceFormat::Rgbx8888 });
+        game.send(Cmd::SafeAreaInsets(insets));
+        game.sync();
+        game.stop();
+
+        assert_eq!(safe_area_insets.get().iter().map(|e| e.0).collect::<Vec<_>>(), vec![insets.scaled(0.5), insets]);
+        assert_eq!(scaled_padding, Some(UiRect::new(Val::Px(0.), Val::Px(0.), Val::Px(20.), Val::Px(10.))));
+        assert_eq!(padding.last().flatten(), Some(insets.as_ui_rect()));
+    }
+
+    static SURFACE_FORMAT_REQUESTS: Mutex<Vec<i32>> = Mutex::new(Vec::new());
+
+    extern "C" fn record_surface_format_request(format: i32) {
+        SURFACE_FORMAT_REQUESTS.lock().unwrap().push(format);
+    }
+
+    #[test]
+    fn surface_format_sets_the_alpha_mode() {
+        set_surface_format_callback(Some(record_surface_format_request));
+        let (game, surface_formats) = attached_app(|app| {
+            app.configure(|app| {
+                app.insert_resource(RequestedSurfaceFormat(Some(SurfaceFormat::Rgba1010102)));
+            });
+            let surface_formats = Recorder::default();
+            let changes = surface_formats.clone();
+            app.configure(move |app| {
+                app.add_systems(Update, move |surface_format: Res<SurfaceFormat>, windows: Query<&Window>| {
+                    if surface_format.is_changed() && !surface_format.is_added() {
+                        let alpha_mode = windows.get_single().map_or(CompositeAlphaMode::Auto, |window| window.composite_alpha_mode);
+                        changes.push([(*surface_format, alpha_mode)]);
+                    }
+                });
+            });
+            surface_formats
+        });
+
+        game.send(Cmd::SurfaceChanged { width: 100, height: 200, scale_factor: 1., format: SurfaceFormat::Rgba1010102 });
+        game.sync();
+        game.stop();
+        set_surface_format_callback(None);
+
+        assert_eq!(surface_formats.get(), vec![
+            (SurfaceFormat::Rgbx8888, CompositeAlphaMode::Opaque),
+            (SurfaceFormat::Rgba1010102, CompositeAlphaMode::Inherit),
+        ]);
+        assert_eq!(*SURFACE_FORMAT_REQUESTS.lock().unwrap(), vec![0x2b]);
+    }
+
+    fn consume_back_press(mut presses: EventReader<BackPressed>, mut consumed: ResMut<BackPressConsumed>) {
+        if presses.read().count() > 0 {
+            consumed.0 = true;
+        }
+    }
+
+    #[test]
+    fn back_press_is_answered() {
+        let back_pressed = |consume: bool| {
+            let mut test_app = TestApp::new();
+            if consume {
+                test_app.configure(|app| {
+                    app.add_systems(Update, consume_back_press);
+                });
+            }
+            let game = test_app.spawn();
+
+            // no surface, nothing runs that could consume it
+            let before_surface = game.back_pressed(Instant::now(), TIMEOUT).unwrap();
+            game.attach(100, 200, 1.);
+            let consumed = game.back_pressed(Instant::now(), TIMEOUT).unwrap();
+            game.stop();
+            (before_surface, consumed)
+        };
+
+        assert_eq!(back_pressed(false), (false, false));
+        assert_eq!(back_pressed(true), (false, true));
+    }
+
+    #[test]
+    fn hit_test_follows_the_configured_regions() {
+        let mut test_app = TestApp::new();
+        test_app.configure(|app| {
+            app.insert_resource(TouchHitTest::Regions(vec![Rect::new(0., 0., 10., 10.)]));
+        });
+        let game = test_app.spawn();
+
+        game.attach(100, 200, 2.);
+        // the regions are published at the end of the update
+        game.sync();
+        let inside = game.hit_test(Vec2::new(15., 15.));
+        let outside = game.hit_test(Vec2::new(25., 15.));
+        let cmd_queue = game.cmd_queue.clone();
+        game.stop();
+
+        assert!(inside);
+        assert!(!outside);
+        // nothing to hit once the game stopped
+        assert!(!cmd_queue.hit_test(Vec2::new(15., 15.)));
+    }
+
+    #[test]
+    fn commands_left_when_the_loop_stops_are_answered() {
+        // a game loop that stops before it handled what the host sent
+        let cmd_queue = CmdQueue::new();
+        *cmd_queue.running_loop.lock().unwrap() = true;
+        let host = |send: fn(&CmdQueue) -> Option<bool>| {
+            let cmd_queue = cmd_queue.clone();
+            thread::spawn(move || send(&cmd_queue))
+        };
+        let hosts = [
+            host(|cmd_queue| { cmd_queue.destroy_surface(); None }),
+            host(|cmd_queue| cmd_queue.redraw(TIMEOUT).ok().map(|()| true)),
+            host(|cmd_queue| cmd_queue.back_pressed(Instant::now(), TIMEOUT).ok()),
+        ];
+        let (released, on_released) = mpsc::channel();
+        cmd_queue.destroy_surface_async(SurfaceRelease::new(move || released.send(()).unwrap()));
+
+        let sent = {
+            let cmd_receiver = cmd_queue.receiver.lock().unwrap();
+            (0..hosts.len() + 1).map(|_| cmd_receiver.recv_timeout(TIMEOUT).unwrap()).collect::<Vec<_>>()
+        };
+        for cmd in sent {
+            cmd_queue.send(cmd);
+        }
+        assert_eq!(on_released.try_recv(), Err(mpsc::TryRecvError::Empty));
+        cmd_queue.stop_running();
+
+        // redrawn and not consumed, instead of timing out
+        assert_eq!(hosts.map(|host| host.join().unwrap()), [None, Some(true), Some(false)]);
+        assert_eq!(on_released.try_recv(), Ok(()));
+    }
+
+    #[test]
+    fn illegal_sequences_are_ignored() {
+        let mut test_app = TestApp::new();
+        let (created, resized_events, states) = (
+            test_app.events::<WindowCreated>(),
+            test_app.events::<WindowResized>(),
+            test_app.events::<SurfaceStateChanged>(),
+        );
+        let game = test_app.spawn();
+
+        game.send(Cmd::SurfaceChanged { width: 100, height: 200, scale_factor: 1., format: SurfaceFormat::Rgbx8888 });
+        game.destroy_surface();
+        game.send(Cmd::SurfaceCreated(Box::new(HeadlessSurface)));
+        game.attach(100, 200, 1.);
+        game.send(Cmd::StopGame);
+        game.stop();
+
+        assert_eq!(created.len(), 1);
+        assert_eq!(resized(&resized_events), vec![(100., 200.)]);
+        assert_eq!(states.get().iter().map(|e| e.to).collect::<Vec<_>>(), vec![
+            SurfaceState::SurfaceAttached,
+            SurfaceState::Running,
+            SurfaceState::Stopping,
+        ]);
+    }
+
+    #[test]
+    fn missing_primary_window_is_reported() {
+        let mut test_app = TestApp::with_window(WindowPlugin {
+            primary_window: None,
+            // only the StopGame command ends it
+            exit_condition: ExitCondition::DontExit,
+            ..default()
+        });
+        let (created, states) = (test_app.events::<WindowCreated>(), test_app.events::<SurfaceStateChanged>());
+        let game = test_app.spawn();
+
+        game.attach(100, 200, 1.);
+        game.stop();
+
+        assert_eq!(created.len(), 0);
+        assert_eq!(states.get().iter().map(|e| e.to).collect::<Vec<_>>(), vec![SurfaceState::Stopping]);
+    }
+
+    #[test]
+    fn resume_keeps_virtual_time_paused_by_the_app() {
+        let time_paused = |paused_by_app: bool| {
+            let (game, time_paused) = attached_app(|app| {
+                if paused_by_app {
+                    app.configure(|app| {
+                        app.world.resource_mut::<Time<Virtual>>().pause();
+                    });
+                }
+                app.sample(|world| world.resource::<Time<Virtual>>().is_paused())
+            });
+
+            game.send(Cmd::OnPause);
+            game.sync();
+            let paused = time_paused.last();
+            game.send(Cmd::OnResume);
+            game.sync();
+            game.stop();
+            (paused, time_paused.last())
+        };
+
+        assert_eq!(time_paused(false), (Some(true), Some(false)));
+        assert_eq!(time_paused(true), (Some(true), Some(true)));
+    }
+
+    #[test]
+    fn reactive_mode_only_updates_on_commands() {
+        let (game, updates) = attached_app(|app| {
+            app.configure(|app| {
+                app.insert_resource(UpdateMode::Reactive { max_wait: Duration::from_secs(3600) });
+            });
+            app.sample(|_| ())
+        });
+
+        // the redraw command also wakes up the loop once
+        thread::sleep(Duration::from_millis(100));
+        let idle_updates = updates.len();
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(updates.len(), idle_updates);
+
+        game.send(Cmd::SetUpdateMode(UpdateMode::Continuous));
+        // updates keep coming without further commands
+        assert!(updates.wait_for_len(idle_updates + 3));
+
+        game.stop();
+    }
+
+    #[test]
+    fn reactive_updates_follow_the_frame_rate() {
+        let (game, updates) = attached_app(|app| {
+            app.configure(|app| {
+                app
+                    .insert_resource(UpdateMode::Reactive { max_wait: Duration::from_secs(3600) })
+                    .insert_resource(FramePacing { target: FrameRate::Fps(5.), ..default() });
+            });
+            app.sample(|_| Instant::now())
+        });
+        let after_redraw = updates.len();
+
+        // a burst of input, less than a frame after the redraw
+        for _ in 0..3 {
+            game.send(touch_event(touch(TouchPhase::Moved, 0, Vec2::ZERO)));
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(updates.len(), after_redraw);
+
+        assert!(updates.wait_for_len(after_redraw + 1));
+        let times = updates.get();
+        // a frame is 200ms, the redraw's update sampled its time a little after it started
+        assert!(times[after_redraw] - times[after_redraw - 1] >= Duration::from_millis(150));
+
+        // one update for the whole burst
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(updates.len(), after_redraw + 1);
+
+        game.stop();
+    }
+
+    #[test]
+    fn redraw_needed_renders_a_frame_before_returning() {
+        let mut test_app = TestApp::new();
+        test_app.configure(|app| {
+            app.insert_resource(UpdateMode::Reactive { max_wait: Duration::from_secs(3600) });
+        });
+        let (updates, resized_events) = (test_app.sample(|_| ()), test_app.events::<WindowResized>());
+        let game = test_app.spawn();
+
+        // nothing to draw without a surface, but it must not block.
+        game.redraw(TIMEOUT).unwrap();
+
+        game.attach(100, 200, 1.);
+        let before = updates.len();
+        game.redraw(TIMEOUT).unwrap();
+        assert!(updates.len() > before);
+
+        // rotated, the frame is drawn at the new size without waiting for the debounce
+        game.send(Cmd::SurfaceChanged { width: 200, height: 100, scale_factor: 1., format: SurfaceFormat::Rgbx8888 });
+        game.redraw(TIMEOUT).unwrap();
+        game.stop();
+
+        assert_eq!(resized(&resized_events), vec![(100., 200.), (200., 100.)]);
+    }
+
+    #[test]
+    fn pause_freezes_until_resume() {
+        let (game, (updates, lifetime)) = attached_app(|app| (app.sample(|_| ()), app.events::<ApplicationLifetime>()));
+
+        game.send(Cmd::OnPause);
+        // the update right after onPause
+        game.sync();
+        let paused_updates = updates.len();
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(updates.len(), paused_updates);
+
+        game.send(Cmd::OnResume);
+        game.sync();
+        let resumed_updates = updates.len();
+        assert!(resumed_updates > paused_updates);
+        // updating again by itself, not only on redraws
+        assert!(updates.wait_for_len(resumed_updates + 2));
+
+        game.stop();
+
+        assert_eq!(lifetime.get(), vec![
+            ApplicationLifetime::Started,
+            ApplicationLifetime::Suspended,
+            ApplicationLifetime::Resumed,
+        ]);
+    }
+}
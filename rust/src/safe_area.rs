@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+
+/// How far the display cutout and the system bars reach into the window, in logical pixels.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct SafeAreaInsets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl SafeAreaInsets {
+    pub fn scaled(self, factor: f32) -> Self {
+        SafeAreaInsets {
+            left: self.left * factor,
+            top: self.top * factor,
+            right: self.right * factor,
+            bottom: self.bottom * factor,
+        }
+    }
+
+    pub fn as_ui_rect(self) -> UiRect {
+        UiRect::new(Val::Px(self.left), Val::Px(self.right), Val::Px(self.top), Val::Px(self.bottom))
+    }
+}
+
+/// Sent when the [`SafeAreaInsets`] resource changes.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct SafeAreaInsetsChanged(pub SafeAreaInsets);
+
+/// Marks a bevy_ui root node whose padding follows the [`SafeAreaInsets`], so its children stay
+/// clear of notches and gesture bars. Any padding set on the node is replaced.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct SafeAreaPadding;
+
+pub(crate) fn apply_safe_area_padding(
+    insets: Res<SafeAreaInsets>,
+    mut nodes: Query<(&mut Style, Ref<SafeAreaPadding>)>,
+) {
+    for (mut style, marker) in &mut nodes {
+        if insets.is_changed() || marker.is_added() {
+            style.padding = insets.as_ui_rect();
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt::Debug;
+use std::io;
+
+use bevy::math::UVec2;
+use bevy::window::RawHandleWrapper;
+#[cfg(target_os = "android")]
+use bevy::prelude::{Deref, DerefMut};
+#[cfg(target_os = "android")]
+use ndk::native_window::NativeWindow;
+#[cfg(target_os = "android")]
+use raw_window_handle::{AndroidDisplayHandle, HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle};
+
+/// A native surface the runner attaches to the primary window.
+pub trait Surface: Debug + Send + 'static {
+    /// The handles bevy renders to, or `None` if the surface can not be rendered to.
+    fn raw_handle(&self) -> Option<RawHandleWrapper>;
+
+    /// Sets the size of the buffers, the compositor scales them to the surface. `None` goes back
+    /// to the size of the surface.
+    fn set_buffer_size(&self, _size: Option<UVec2>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The `ANativeWindow` of an android `Surface`.
+#[cfg(target_os = "android")]
+#[derive(Debug, Deref, DerefMut)]
+pub struct AndroidSurface(NativeWindow);
+
+#[cfg(target_os = "android")]
+impl From<NativeWindow> for AndroidSurface {
+    fn from(value: NativeWindow) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(target_os = "android")]
+unsafe impl HasRawDisplayHandle for AndroidSurface {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        RawDisplayHandle::Android(AndroidDisplayHandle::empty())
+    }
+}
+
+#[cfg(target_os = "android")]
+impl Surface for AndroidSurface {
+    fn raw_handle(&self) -> Option<RawHandleWrapper> {
+        Some(RawHandleWrapper {
+            window_handle: self.raw_window_handle(),
+            display_handle: self.raw_display_handle(),
+        })
+    }
+
+    fn set_buffer_size(&self, size: Option<UVec2>) -> io::Result<()> {
+        let size = size.unwrap_or(UVec2::ZERO);
+        self.set_buffers_geometry(size.x as i32, size.y as i32, None)
+    }
+}
+
+/// A surface without a native window, nothing is rendered to it.
+///
+/// Lets the runner be driven off-device, e.g. from `cargo test` with the render plugins disabled.
+#[derive(Debug, Default)]
+pub struct HeadlessSurface;
+
+impl Surface for HeadlessSurface {
+    fn raw_handle(&self) -> Option<RawHandleWrapper> {
+        None
+    }
+}
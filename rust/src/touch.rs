@@ -0,0 +1,180 @@
+use std::time::Instant;
+
+use bevy::input::touch::{ForceTouch, TouchPhase};
+use bevy::math::vec2;
+use bevy::prelude::*;
+
+use crate::error::BridgeError;
+
+/// Floats per pointer in a batch packed by the host: id, x, y, pressure, size, tool type, tilt.
+pub const POINTER_STRIDE: usize = 7;
+
+// `MotionEvent.TOOL_TYPE_*` of pens, they report their tilt.
+const TOOL_TYPE_STYLUS: f32 = 2.;
+const TOOL_TYPE_ERASER: f32 = 4.;
+
+// `MotionEvent` actions, masked with `ACTION_MASK`.
+const ACTION_DOWN: i32 = 0;
+const ACTION_UP: i32 = 1;
+const ACTION_MOVE: i32 = 2;
+const ACTION_CANCEL: i32 = 3;
+const ACTION_POINTER_DOWN: i32 = 5;
+const ACTION_POINTER_UP: i32 = 6;
+const ACTION_BUTTON_RELEASE: i32 = 12;
+
+/// One pointer of a `MotionEvent` at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchSample {
+    pub input: TouchInput,
+    /// `MotionEvent.getSize`, the normalized area of the screen covered by the pointer.
+    pub size: f32,
+    /// When the host saw the sample, from `MotionEvent.getEventTime` or its historical times.
+    pub time: Instant,
+}
+
+/// What [`TouchInput`] leaves out of a sample, sent right after the [`TouchInput`] of the same
+/// sample.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct TouchDetails {
+    pub id: u64,
+    pub size: f32,
+    pub time: Instant,
+}
+
+/// Fingers report a normalized pressure, styluses also their altitude, from `AXIS_TILT` which is
+/// the angle away from the perpendicular.
+fn force(pressure: f32, tool_type: f32, tilt: f32) -> ForceTouch {
+    let force = pressure.clamp(0., 1.) as f64;
+    if tool_type == TOOL_TYPE_STYLUS || tool_type == TOOL_TYPE_ERASER {
+        ForceTouch::Calibrated {
+            force,
+            max_possible_force: 1.,
+            altitude_angle: Some(std::f64::consts::FRAC_PI_2 - tilt as f64),
+        }
+    } else {
+        ForceTouch::Normalized(force)
+    }
+}
+
+/// The phase a `MotionEvent` action maps to, `None` for actions that are not touches (hover,
+/// scroll, buttons).
+pub fn touch_phase(action: i32) -> Result<Option<TouchPhase>, BridgeError> {
+    match action {
+        ACTION_DOWN | ACTION_POINTER_DOWN => Ok(Some(TouchPhase::Started)),
+        ACTION_UP | ACTION_POINTER_UP => Ok(Some(TouchPhase::Ended)),
+        ACTION_MOVE => Ok(Some(TouchPhase::Moved)),
+        ACTION_CANCEL => Ok(Some(TouchPhase::Canceled)),
+        0..=ACTION_BUTTON_RELEASE => Ok(None),
+        _ => Err(BridgeError::UnknownMotionAction(action)),
+    }
+}
+
+/// Unpacks a whole `MotionEvent`.
+///
+/// `data` holds `history_size + 1` samples, oldest first, each with `pointer_count` pointers of
+/// [`POINTER_STRIDE`] floats, and `times` the time of each sample. Moves forward every pointer of every sample, down and up only the
+/// pointer at `action_index`, cancel every pointer of the current sample.
+pub fn unpack_motion_event(
+    action: i32,
+    action_index: usize,
+    pointer_count: usize,
+    history_size: usize,
+    data: &[f32],
+    times: &[Instant],
+) -> Result<Vec<TouchSample>, BridgeError> {
+    let sample_len = pointer_count * POINTER_STRIDE;
+    if pointer_count == 0
+        || data.len() != sample_len * (history_size + 1)
+        || times.len() != history_size + 1
+        || action_index >= pointer_count
+    {
+        return Err(BridgeError::MalformedTouchData);
+    }
+
+    let Some(phase) = touch_phase(action)? else {
+        return Ok(Vec::new());
+    };
+
+    let pointer = |sample: usize, index: usize| {
+        let p = &data[sample * sample_len + index * POINTER_STRIDE..][..POINTER_STRIDE];
+        TouchSample {
+            input: TouchInput {
+                phase,
+                position: vec2(p[1], p[2]),
+                force: Some(force(p[3], p[5], p[6])),
+                id: p[0] as u64,
+            },
+            size: p[4],
+            time: times[sample],
+        }
+    };
+
+    let current = history_size;
+    let samples = match phase {
+        TouchPhase::Moved => (0..=history_size)
+            .flat_map(|sample| (0..pointer_count).map(move |index| pointer(sample, index)))
+            .collect(),
+        TouchPhase::Canceled => (0..pointer_count).map(|index| pointer(current, index)).collect(),
+        TouchPhase::Started | TouchPhase::Ended => vec![pointer(current, action_index)],
+    };
+
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::input::touch::{ForceTouch, TouchPhase};
+
+    use crate::error::BridgeError;
+
+    use super::unpack_motion_event;
+
+    #[test]
+    fn moves_forward_every_pointer_and_sample() {
+        let data = [
+            // historical sample
+            0., 1., 2., 0.5, 0.1, 1., 0., 4., 3., 4., 1.5, 0.2, 1., 0.,
+            // current sample
+            0., 5., 6., 0.5, 0.1, 1., 0., 4., 7., 8., 0.25, 0.2, 2., 0.5,
+        ];
+        let now = Instant::now();
+        let times = [now - Duration::from_millis(8), now];
+        let samples = unpack_motion_event(2, 0, 2, 1, &data, &times).unwrap();
+
+        let ids: Vec<_> = samples.iter().map(|s| s.input.id).collect();
+        let xs: Vec<_> = samples.iter().map(|s| s.input.position.x).collect();
+        assert_eq!(ids, vec![0, 4, 0, 4]);
+        assert_eq!(xs, vec![1., 3., 5., 7.]);
+        assert!(samples.iter().all(|s| s.input.phase == TouchPhase::Moved));
+        assert_eq!(samples[1].input.force, Some(ForceTouch::Normalized(1.)));
+        assert_eq!(samples[3].size, 0.2);
+        assert_eq!(samples.iter().map(|s| s.time).collect::<Vec<_>>(), vec![times[0], times[0], now, now]);
+        assert_eq!(samples[3].input.force, Some(ForceTouch::Calibrated {
+            force: 0.25,
+            max_possible_force: 1.,
+            altitude_angle: Some(std::f64::consts::FRAC_PI_2 - 0.5),
+        }));
+    }
+
+    #[test]
+    fn pointer_down_forwards_only_the_action_pointer() {
+        let data = [0., 1., 2., 1., 0.1, 1., 0., 4., 3., 4., 1., 0.2, 1., 0.];
+        let samples = unpack_motion_event(5, 1, 2, 0, &data, &[Instant::now()]).unwrap();
+
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].input.id, 4);
+        assert_eq!(samples[0].input.phase, TouchPhase::Started);
+    }
+
+    #[test]
+    fn malformed_batches_are_rejected() {
+        let times = [Instant::now()];
+        assert_eq!(unpack_motion_event(2, 0, 2, 0, &[0.; 7], &times), Err(BridgeError::MalformedTouchData));
+        assert_eq!(unpack_motion_event(5, 2, 2, 0, &[0.; 14], &times), Err(BridgeError::MalformedTouchData));
+        assert_eq!(unpack_motion_event(2, 0, 1, 1, &[0.; 14], &times), Err(BridgeError::MalformedTouchData));
+        assert_eq!(unpack_motion_event(42, 0, 1, 0, &[0.; 7], &times), Err(BridgeError::UnknownMotionAction(42)));
+        assert_eq!(unpack_motion_event(7, 0, 1, 0, &[0.; 7], &times), Ok(Vec::new()));
+    }
+}